@@ -0,0 +1,70 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::RateMetric;
+use core::sync::atomic::{AtomicU32, Ordering};
+use monotonic_time_rs::Millis;
+
+/// A [`RateMetric`] whose events can be recorded from many threads without locking.
+///
+/// [`AtomicRateMetric::increment`] and [`AtomicRateMetric::add`] take `&self` and use a
+/// relaxed atomic add. Relaxed ordering guarantees that every recorded event is counted
+/// exactly once, but it does not order the recording against any other memory accesses.
+/// [`AtomicRateMetric::update`] takes `&mut self`, so the events recorded before it are
+/// always included in the window that it closes.
+#[derive(Debug)]
+pub struct AtomicRateMetric {
+    pending: AtomicU32,
+    rate: RateMetric,
+}
+
+impl AtomicRateMetric {
+    /// Creates a new `AtomicRateMetric` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The initial [`Millis`] from which time tracking starts.
+    pub fn new(time: Millis) -> Self {
+        Self::from_rate_metric(RateMetric::new(time))
+    }
+
+    /// Creates a new `AtomicRateMetric` from a configured [`RateMetric`], e.g. one with a
+    /// custom interval or smoothing.
+    pub fn from_rate_metric(rate: RateMetric) -> Self {
+        Self {
+            pending: AtomicU32::new(0),
+            rate,
+        }
+    }
+
+    /// Increments the event count by one.
+    pub fn increment(&self) {
+        self.add(1);
+    }
+
+    /// Adds a specified number of events to the count.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of events to add.
+    pub fn add(&self, count: u32) {
+        self.pending.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Moves the recorded events into the rate calculation and updates it, like
+    /// [`RateMetric::update`].
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The current [`Millis`] representing the time at which the update is triggered.
+    pub fn update(&mut self, time: Millis) {
+        let pending = core::mem::take(self.pending.get_mut());
+        self.rate.add(pending);
+        self.rate.update(time);
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate.rate()
+    }
+}
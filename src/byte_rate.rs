@@ -0,0 +1,77 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::RateMetric;
+use core::fmt::{Debug, Display};
+use num_traits::{PrimInt, Unsigned, WrappingAdd};
+
+/// The unit prefixes used by [`ByteRate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BytePrefix {
+    /// Powers of 1024, `KiB`, `MiB`, `GiB` and `TiB`.
+    Binary,
+    /// Powers of 1000, `kB`, `MB`, `GB` and `TB`.
+    Decimal,
+}
+
+/// Displays a rate of bytes per second with a unit prefix, e.g. `1.5 MiB/s`.
+///
+/// Rates that round to less than one kilo- or kibibyte are displayed as whole bytes, e.g.
+/// `512 B/s`, and larger rates with one decimal. The prefix is chosen after rounding, so
+/// that the value never reaches the base, e.g. `1.0 MiB/s` instead of `1024.0 KiB/s`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteRate {
+    bytes_per_second: f32,
+    prefix: BytePrefix,
+}
+
+impl ByteRate {
+    pub const fn new(bytes_per_second: f32, prefix: BytePrefix) -> Self {
+        Self {
+            bytes_per_second,
+            prefix,
+        }
+    }
+
+    /// Returns the rate that is displayed.
+    pub fn bytes_per_second(&self) -> f32 {
+        self.bytes_per_second
+    }
+}
+
+impl Display for ByteRate {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (base, units) = match self.prefix {
+            BytePrefix::Binary => (1024.0, ["KiB", "MiB", "GiB", "TiB"]),
+            BytePrefix::Decimal => (1000.0, ["kB", "MB", "GB", "TB"]),
+        };
+
+        // The unit is chosen after rounding, so that e.g. 1023.6 B/s is displayed as
+        // 1.0 KiB/s instead of 1024 B/s
+        if self.bytes_per_second.round().abs() < base {
+            return write!(f, "{:.0} B/s", self.bytes_per_second);
+        }
+
+        let mut value = self.bytes_per_second / base;
+        let mut unit = units[0];
+        for next_unit in &units[1..] {
+            if ((value * 10.0).round() / 10.0).abs() < base {
+                break;
+            }
+            value /= base;
+            unit = next_unit;
+        }
+        write!(f, "{value:.1} {unit}/s")
+    }
+}
+
+impl<C> RateMetric<C>
+where
+    C: PrimInt + Unsigned + WrappingAdd + Debug,
+{
+    /// Returns the rate as a [`ByteRate`], for a metric that counts bytes.
+    pub fn byte_rate(&self, prefix: BytePrefix) -> ByteRate {
+        ByteRate::new(self.rate(), prefix)
+    }
+}
@@ -0,0 +1,43 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+/// A monotonically increasing counter, e.g. the total number of bytes sent.
+///
+/// The counter wraps around at `u64::MAX`, and [`Counter::delta_since`] accounts for that
+/// when computing the difference to a previous reading.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counter {
+    value: u64,
+}
+
+impl Counter {
+    /// Creates a new `Counter` starting at zero.
+    pub const fn new() -> Self {
+        Self { value: 0 }
+    }
+
+    /// Increments the counter by one.
+    pub fn inc(&mut self) {
+        self.add(1);
+    }
+
+    /// Adds `count` to the counter, wrapping around at `u64::MAX`.
+    pub fn add(&mut self, count: u64) {
+        self.value = self.value.wrapping_add(count);
+    }
+
+    /// Returns the current value of the counter.
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Returns how much the counter has increased since the `previous` reading.
+    ///
+    /// If the counter has wrapped around since `previous`, the delta across the wrap is
+    /// returned. The counter is assumed to have wrapped at most once.
+    pub fn delta_since(&self, previous: u64) -> u64 {
+        self.value.wrapping_sub(previous)
+    }
+}
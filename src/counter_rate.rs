@@ -0,0 +1,60 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use monotonic_time_rs::Millis;
+
+/// Calculates a per second rate from successive readings of a cumulative counter, e.g. the
+/// total number of packets received reported by the OS.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CounterRate {
+    previous: Option<(u64, Millis)>,
+    rate: f32,
+}
+
+impl CounterRate {
+    /// Creates a new `CounterRate` without any previous reading.
+    pub const fn new() -> Self {
+        Self {
+            previous: None,
+            rate: 0.0,
+        }
+    }
+
+    /// Stores the reading and returns the rate since the previous reading.
+    ///
+    /// # Arguments
+    ///
+    /// * `total` - The current value of the cumulative counter.
+    /// * `now` - The [`Millis`] at which the counter was read.
+    ///
+    /// # Returns
+    ///
+    /// The increase of the counter divided by the elapsed seconds. The first reading returns
+    /// `0.0`, since there is nothing to compare against. If the counter decreased, e.g.
+    /// because the source was restarted, or if time went backwards, the reading starts a
+    /// new baseline and `0.0` is returned. If no time has elapsed, the previous rate is
+    /// returned and the reading is ignored.
+    pub fn rate_from_counter(&mut self, total: u64, now: Millis) -> f32 {
+        let Some((previous_total, previous_time)) = self.previous else {
+            self.previous = Some((total, now));
+            return 0.0;
+        };
+
+        let elapsed = now.checked_duration_since_ms(previous_time);
+        match elapsed {
+            Some(elapsed) if elapsed.as_millis() == 0 => return self.rate,
+            Some(elapsed) if total >= previous_total => {
+                self.rate = (total - previous_total) as f32 / elapsed.as_secs();
+            }
+            _ => self.rate = 0.0,
+        }
+        self.previous = Some((total, now));
+        self.rate
+    }
+
+    /// Returns the rate calculated by the last call to [`CounterRate::rate_from_counter`].
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+}
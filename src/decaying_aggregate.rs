@@ -0,0 +1,106 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::MinMaxAvg;
+use monotonic_time_rs::{Millis, MillisDuration};
+
+/// Tracks exponentially decayed minimum, maximum, and average values, for a smooth view of
+/// the recent past instead of a hard window.
+///
+/// Before every new sample, the accumulated sum and count are scaled by
+/// `exp(-elapsed / time_constant)`, and the minimum and maximum move the same fraction of
+/// the way toward the average. A sample that is `time_constant` old thus only has about
+/// 37% of the weight of a new one.
+#[derive(Debug)]
+pub struct DecayingAggregate {
+    time_constant: MillisDuration,
+    sum: f64,
+    count: f64,
+    min: f64,
+    max: f64,
+    last_added_at: Option<Millis>,
+    unit: &'static str,
+}
+
+impl DecayingAggregate {
+    /// Creates a new `DecayingAggregate` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_constant` - The duration over which the accumulated values decay to `1/e`.
+    ///   A zero duration only keeps the latest sample.
+    pub const fn new(time_constant: MillisDuration) -> Self {
+        Self {
+            time_constant,
+            sum: 0.0,
+            count: 0.0,
+            min: 0.0,
+            max: 0.0,
+            last_added_at: None,
+            unit: "",
+        }
+    }
+
+    pub fn with_unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Decays the accumulated values to `now` and adds `value` to them.
+    ///
+    /// Non-finite values are ignored. A `now` earlier than the previous sample is treated
+    /// as no time having elapsed.
+    pub fn add(&mut self, now: Millis, value: f32) {
+        if !value.is_finite() {
+            return;
+        }
+        let value = f64::from(value);
+
+        let Some(last_added_at) = self.last_added_at else {
+            self.sum = value;
+            self.count = 1.0;
+            self.min = value;
+            self.max = value;
+            self.last_added_at = Some(now);
+            return;
+        };
+
+        let elapsed = now
+            .checked_duration_since_ms(last_added_at)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        let decay = if self.time_constant.as_millis() == 0 {
+            0.0
+        } else {
+            (-(elapsed as f64) / self.time_constant.as_millis() as f64).exp()
+        };
+
+        let mean = self.sum / self.count;
+        self.sum *= decay;
+        self.count *= decay;
+        self.min = mean + (self.min - mean) * decay;
+        self.max = mean + (self.max - mean) * decay;
+
+        self.sum += value;
+        self.count += 1.0;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if elapsed > 0 {
+            self.last_added_at = Some(now);
+        }
+    }
+
+    /// Returns the decayed minimum, average, and maximum values as of the latest sample, if
+    /// any samples have been added.
+    pub fn values(&self) -> Option<MinMaxAvg<f32>> {
+        self.last_added_at?;
+        Some(
+            MinMaxAvg::new(
+                self.min as f32,
+                (self.sum / self.count) as f32,
+                self.max as f32,
+            )
+            .with_unit(self.unit),
+        )
+    }
+}
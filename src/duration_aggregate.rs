@@ -0,0 +1,71 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::{AggregateMetric, MetricError, MinMaxAvg};
+use core::fmt::Display;
+use monotonic_time_rs::MillisDuration;
+
+/// Tracks minimum, maximum, and average of durations, e.g. frame or request times.
+///
+/// Built on an [`AggregateMetric<u64>`] of milliseconds, with the `ms` unit.
+#[derive(Debug)]
+pub struct DurationAggregate {
+    metric: AggregateMetric<u64>,
+}
+
+impl DurationAggregate {
+    /// Creates a new `DurationAggregate` that completes a window every `threshold` durations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `threshold` is zero.
+    pub fn new(threshold: u32) -> Result<Self, MetricError> {
+        Ok(Self {
+            metric: AggregateMetric::new(threshold)?.with_unit("ms"),
+        })
+    }
+
+    /// Adds a duration to the window in progress.
+    pub fn add(&mut self, duration: MillisDuration) {
+        self.metric.add(duration.as_millis());
+    }
+
+    /// Returns the shortest duration of the last completed window.
+    pub fn min(&self) -> Option<MillisDuration> {
+        self.metric
+            .values()
+            .map(|values| MillisDuration::from_millis(values.min))
+    }
+
+    /// Returns the longest duration of the last completed window.
+    pub fn max(&self) -> Option<MillisDuration> {
+        self.metric
+            .values()
+            .map(|values| MillisDuration::from_millis(values.max))
+    }
+
+    /// Returns the average duration of the last completed window, rounded to the nearest
+    /// millisecond.
+    pub fn average(&self) -> Option<MillisDuration> {
+        self.metric
+            .average()
+            .map(|avg| MillisDuration::from_millis(avg.round() as u64))
+    }
+
+    /// Returns the milliseconds of the last completed window, with the `ms` unit.
+    pub fn values(&self) -> Option<MinMaxAvg<u64>> {
+        self.metric.values()
+    }
+
+    /// Returns the underlying metric of milliseconds.
+    pub fn metric(&self) -> &AggregateMetric<u64> {
+        &self.metric
+    }
+}
+
+impl Display for DurationAggregate {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.metric)
+    }
+}
@@ -0,0 +1,57 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use core::fmt::Display;
+
+/// Errors returned when constructing or feeding metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MetricError {
+    /// A threshold of zero samples would never complete a window.
+    ZeroThreshold,
+    /// A buffer capacity of zero can not hold any samples.
+    ZeroCapacity,
+    /// A smoothing factor was outside of `0.0..=1.0`.
+    InvalidAlpha,
+    /// A quantile was not strictly between `0.0` and `1.0`.
+    InvalidQuantile,
+    /// A trim fraction was outside of `0.0..0.5`.
+    InvalidTrimFraction,
+    /// Histogram bucket boundaries were not strictly increasing.
+    UnsortedBoundaries,
+    /// A value was NaN or infinite.
+    NonFinite,
+    /// A value was zero or negative where only positive values are supported.
+    NonPositive,
+    /// A value was zero where it would cause a division by zero.
+    Zero,
+    /// Adding a value would overflow the sum.
+    Overflow,
+    /// A measurement interval was shorter than one millisecond.
+    InvalidInterval,
+    /// Metrics that are combined are configured differently.
+    Incompatible,
+}
+
+impl Display for MetricError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let message = match self {
+            Self::ZeroThreshold => "threshold can not be zero",
+            Self::ZeroCapacity => "capacity can not be zero",
+            Self::InvalidAlpha => "alpha must be between 0.0 and 1.0",
+            Self::InvalidQuantile => "quantile must be between 0.0 and 1.0",
+            Self::InvalidTrimFraction => "trim fraction must be between 0.0 and 0.5",
+            Self::UnsortedBoundaries => "boundaries must be strictly increasing",
+            Self::NonFinite => "value is not finite",
+            Self::NonPositive => "value must be positive",
+            Self::Zero => "value can not be zero",
+            Self::Overflow => "sum would overflow",
+            Self::InvalidInterval => "interval must be at least one millisecond",
+            Self::Incompatible => "metrics are configured differently",
+        };
+        f.write_str(message)
+    }
+}
+
+impl core::error::Error for MetricError {}
@@ -0,0 +1,68 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::MetricError;
+use monotonic_time_rs::Millis;
+use std::collections::VecDeque;
+
+/// Calculates a rate from the timestamps of the most recent events.
+///
+/// Unlike [`crate::RateMetric`], which counts events within measurement intervals, the rate
+/// is calculated on demand from the time span of the buffered events. This reacts faster
+/// to bursty or infrequent events.
+#[derive(Debug)]
+pub struct EventRate {
+    timestamps: VecDeque<Millis>,
+    capacity: usize,
+}
+
+impl EventRate {
+    /// Creates a new `EventRate` that retains the timestamps of the last `capacity` events.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `capacity` is zero.
+    pub fn new(capacity: usize) -> Result<Self, MetricError> {
+        if capacity == 0 {
+            return Err(MetricError::ZeroCapacity);
+        }
+        Ok(Self {
+            timestamps: VecDeque::with_capacity(capacity),
+            capacity,
+        })
+    }
+
+    /// Records an event at `now`, evicting the oldest timestamp if the buffer is full.
+    pub fn record(&mut self, now: Millis) {
+        if self.timestamps.len() == self.capacity {
+            self.timestamps.pop_front();
+        }
+        self.timestamps.push_back(now);
+    }
+
+    /// Returns the number of events per second over the span of the buffered events.
+    ///
+    /// The `n` buffered events are separated by `n - 1` intervals, so the rate is
+    /// `(n - 1) / (latest - oldest)`. Returns `None` if fewer than two events have been
+    /// recorded or if they all have the same timestamp.
+    pub fn rate(&self) -> Option<f32> {
+        let oldest = *self.timestamps.front()?;
+        let latest = *self.timestamps.back()?;
+        let span = latest.checked_duration_since_ms(oldest)?;
+        if span.as_millis() == 0 {
+            return None;
+        }
+        Some((self.timestamps.len() - 1) as f32 / span.as_secs())
+    }
+
+    /// Returns the number of buffered timestamps.
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Returns `true` if no events have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+}
@@ -0,0 +1,50 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::MetricError;
+
+/// Smooths a noisy signal with an exponentially weighted moving average.
+#[derive(Debug, Clone, Copy)]
+pub struct EwmaMetric {
+    value: f32,
+    alpha: f32,
+    initialized: bool,
+}
+
+impl EwmaMetric {
+    /// Creates a new `EwmaMetric` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - The weight of each new sample, in the range `0.0..=1.0`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `alpha` is outside of `0.0..=1.0`.
+    pub fn new(alpha: f32) -> Result<Self, MetricError> {
+        if !(0.0..=1.0).contains(&alpha) {
+            return Err(MetricError::InvalidAlpha);
+        }
+        Ok(Self {
+            value: 0.0,
+            alpha,
+            initialized: false,
+        })
+    }
+
+    /// Blends a sample into the average. The first sample initializes the average directly.
+    pub fn update(&mut self, sample: f32) {
+        if self.initialized {
+            self.value = self.alpha * sample + (1.0 - self.alpha) * self.value;
+        } else {
+            self.value = sample;
+            self.initialized = true;
+        }
+    }
+
+    /// Returns the average, or `None` if no samples have been added.
+    pub fn value(&self) -> Option<f32> {
+        self.initialized.then_some(self.value)
+    }
+}
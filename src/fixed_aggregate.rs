@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::MinMaxAvg;
+use core::cmp::Ordering;
+use core::fmt::Display;
+use num_traits::ToPrimitive;
+
+/// Tracks minimum, maximum, average and median values over the most recent `N` samples,
+/// without any heap allocation.
+///
+/// Like [`crate::SlidingAggregate`], the oldest sample is evicted for every new sample once
+/// the buffer is full.
+#[derive(Debug, Clone)]
+pub struct FixedAggregate<T, const N: usize> {
+    samples: [T; N],
+    len: usize,
+    next: usize,
+    unit: &'static str,
+}
+
+impl<T, const N: usize> FixedAggregate<T, N>
+where
+    T: Copy + PartialOrd + Default + Display + ToPrimitive,
+{
+    /// Creates a new `FixedAggregate` that retains the last `N` samples.
+    pub fn new() -> Self {
+        const { assert!(N > 0, "capacity can not be zero") };
+        Self {
+            samples: [T::default(); N],
+            len: 0,
+            next: 0,
+            unit: "",
+        }
+    }
+
+    pub fn with_unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Adds a value, overwriting the oldest one if the buffer is full.
+    pub fn add(&mut self, value: T) {
+        self.samples[self.next] = value;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Returns the number of samples currently in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no samples have been added.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the minimum, average, and maximum values over the buffer, if it has at
+    /// least one sample.
+    pub fn values(&self) -> Option<MinMaxAvg<T>> {
+        let samples = &self.samples[..self.len];
+        let (&first, rest) = samples.split_first()?;
+
+        let mut min = first;
+        let mut max = first;
+        let mut sum = first.to_f64().unwrap_or(0.0);
+        for &sample in rest {
+            if sample < min {
+                min = sample;
+            }
+            if sample > max {
+                max = sample;
+            }
+            sum += sample.to_f64().unwrap_or(0.0);
+        }
+
+        let avg = (sum / self.len as f64) as f32;
+        Some(MinMaxAvg::new(min, avg, max).with_unit(self.unit))
+    }
+
+    /// Calculates the median over the buffer, returning `None` if it is empty.
+    ///
+    /// For an even number of samples, the two middle values are interpolated.
+    pub fn median(&self) -> Option<f32> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let mut sorted = self.samples;
+        let sorted = &mut sorted[..self.len];
+        sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        let upper = sorted[self.len / 2].to_f64()?;
+        if self.len % 2 == 1 {
+            Some(upper as f32)
+        } else {
+            let lower = sorted[self.len / 2 - 1].to_f64()?;
+            Some(((lower + upper) / 2.0) as f32)
+        }
+    }
+}
+
+impl<T, const N: usize> Default for FixedAggregate<T, N>
+where
+    T: Copy + PartialOrd + Default + Display + ToPrimitive,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
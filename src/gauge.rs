@@ -0,0 +1,78 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use core::fmt::Display;
+
+/// Holds the latest value, together with the lowest and highest values ever set.
+///
+/// Unlike [`crate::AggregateMetric`] it never averages or resets.
+#[derive(Debug, Clone, Copy)]
+pub struct Gauge<T> {
+    value: Option<T>,
+    min: Option<T>,
+    max: Option<T>,
+    unit: &'static str,
+}
+
+impl<T: Copy + PartialOrd> Gauge<T> {
+    /// Creates a new `Gauge` without a value.
+    pub const fn new() -> Self {
+        Self {
+            value: None,
+            min: None,
+            max: None,
+            unit: "",
+        }
+    }
+
+    pub fn with_unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Sets the current value and updates the lifetime extremes.
+    pub fn set(&mut self, value: T) {
+        self.value = Some(value);
+        if self.min.is_none_or(|min| value < min) {
+            self.min = Some(value);
+        }
+        if self.max.is_none_or(|max| value > max) {
+            self.max = Some(value);
+        }
+    }
+
+    /// Returns the latest value, or `None` if no value has been set.
+    pub fn value(&self) -> Option<T> {
+        self.value
+    }
+
+    /// Returns the lowest value ever set.
+    pub fn min(&self) -> Option<T> {
+        self.min
+    }
+
+    /// Returns the highest value ever set.
+    pub fn max(&self) -> Option<T> {
+        self.max
+    }
+}
+
+impl<T: Copy + PartialOrd> Default for Gauge<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Display> Display for Gauge<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match (&self.value, &self.min, &self.max) {
+            (Some(value), Some(min), Some(max)) => write!(
+                f,
+                "value:{}{} (min:{}{} max:{}{})",
+                value, self.unit, min, self.unit, max, self.unit
+            ),
+            _ => write!(f, "value:none"),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! Renders metrics in the Graphite plaintext protocol, `path value timestamp\n`.
+//!
+//! Graphite expects Unix timestamps in seconds, so the [`Millis`] passed in, or stored in
+//! the metric, must count from the Unix epoch. Sub-second precision is truncated.
+
+use crate::{AggregateMetric, RateMetric};
+use core::fmt::{Debug, Display};
+use core::ops::{Add, Div, Sub};
+use monotonic_time_rs::Millis;
+use num_traits::{Bounded, ToPrimitive};
+
+/// Formats a single line, `path value timestamp\n`.
+pub fn line(path: &str, value: impl Display, timestamp: Millis) -> String {
+    format!(
+        "{path} {value} {}\n",
+        timestamp.absolute_milliseconds() / 1000
+    )
+}
+
+/// Formats the rate of a [`RateMetric`], timestamped with its [last update](RateMetric::last_update).
+pub fn rate(path: &str, rate: &RateMetric) -> String {
+    line(path, rate.rate(), rate.last_update())
+}
+
+/// Formats the last completed window of an [`AggregateMetric`] as three lines, for
+/// `path.min`, `path.avg` and `path.max`.
+///
+/// Returns an empty string if the aggregate hasn't completed a window yet.
+pub fn aggregate<T>(path: &str, aggregate: &AggregateMetric<T>, timestamp: Millis) -> String
+where
+    T: Add<Output = T>
+        + Sub<Output = T>
+        + Div<Output = T>
+        + Copy
+        + PartialOrd
+        + Default
+        + From<u8>
+        + Debug
+        + Display
+        + Bounded
+        + ToPrimitive,
+{
+    let Some(values) = aggregate.values() else {
+        return String::new();
+    };
+    let mut out = line(&format!("{path}.min"), values.min, timestamp);
+    out += &line(&format!("{path}.avg"), values.avg, timestamp);
+    out += &line(&format!("{path}.max"), values.max, timestamp);
+    out
+}
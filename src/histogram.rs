@@ -0,0 +1,80 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::MetricError;
+use core::fmt::Display;
+
+/// Counts how many values fall into each of a set of buckets.
+///
+/// Each bucket counts the values that are less than or equal to its upper boundary and
+/// greater than the boundary of the previous bucket. Values above the last boundary are
+/// counted in an extra overflow bucket.
+#[derive(Debug)]
+pub struct Histogram<T> {
+    boundaries: Vec<T>,
+    buckets: Vec<u64>,
+}
+
+impl<T> Histogram<T>
+where
+    T: Copy + PartialOrd + Display,
+{
+    /// Creates a new `Histogram` with the given upper bucket boundaries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the boundaries are not strictly increasing.
+    pub fn new(boundaries: &[T]) -> Result<Self, MetricError> {
+        if boundaries.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(MetricError::UnsortedBoundaries);
+        }
+
+        Ok(Self {
+            boundaries: boundaries.to_vec(),
+            buckets: vec![0; boundaries.len() + 1],
+        })
+    }
+
+    /// Increments the count of the bucket that `value` falls into.
+    pub fn record(&mut self, value: T) {
+        let index = self
+            .boundaries
+            .iter()
+            .position(|&boundary| value <= boundary)
+            .unwrap_or(self.boundaries.len());
+        self.buckets[index] += 1;
+    }
+
+    /// Returns the upper boundaries of the buckets.
+    pub fn boundaries(&self) -> &[T] {
+        &self.boundaries
+    }
+
+    /// Returns the count of each bucket, followed by the count of the overflow bucket.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Returns the total number of recorded values.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+}
+
+impl<T: Display> Display for Histogram<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (boundary, count) in self.boundaries.iter().zip(&self.buckets) {
+            writeln!(f, "bucket<={}: {}", boundary, count)?;
+        }
+        match self.boundaries.last() {
+            Some(last) => write!(
+                f,
+                "bucket>{}: {}",
+                last,
+                self.buckets[self.boundaries.len()]
+            ),
+            None => write!(f, "bucket: {}", self.buckets[0]),
+        }
+    }
+}
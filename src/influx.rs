@@ -0,0 +1,76 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! Renders metrics in the InfluxDB line protocol,
+//! `measurement,tag=value min=2,avg=5,max=8 timestamp`.
+//!
+//! Influx expects Unix timestamps in nanoseconds, so the [`Millis`] passed in must count
+//! from the Unix epoch.
+
+use crate::AggregateMetric;
+use core::fmt::{Debug, Display, Write};
+use core::ops::{Add, Div, Sub};
+use monotonic_time_rs::Millis;
+use num_traits::{Bounded, ToPrimitive};
+
+/// A tag key and value attached to the line.
+pub type Tag<'a> = (&'a str, &'a str);
+
+/// Formats the last completed window of an [`AggregateMetric`] as a line with the fields
+/// `min`, `avg` and `max`.
+///
+/// Returns an empty string if the aggregate hasn't completed a window yet.
+pub fn aggregate<T>(
+    measurement: &str,
+    tags: &[Tag],
+    aggregate: &AggregateMetric<T>,
+    timestamp: Millis,
+) -> String
+where
+    T: Add<Output = T>
+        + Sub<Output = T>
+        + Div<Output = T>
+        + Copy
+        + PartialOrd
+        + Default
+        + From<u8>
+        + Debug
+        + Display
+        + Bounded
+        + ToPrimitive,
+{
+    let Some(values) = aggregate.values() else {
+        return String::new();
+    };
+    let mut out = escape(measurement, &[',', ' ']);
+    for (key, value) in tags {
+        let _ = write!(
+            out,
+            ",{}={}",
+            escape(key, &[',', '=', ' ']),
+            escape(value, &[',', '=', ' '])
+        );
+    }
+    let _ = writeln!(
+        out,
+        " min={},avg={},max={} {}",
+        values.min,
+        values.avg,
+        values.max,
+        u128::from(timestamp.absolute_milliseconds()) * 1_000_000
+    );
+    out
+}
+
+fn escape(s: &str, special: &[char]) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if special.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
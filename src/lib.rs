@@ -4,18 +4,84 @@ use monotonic_time_rs::MillisDuration;
  * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
+use core::cmp::Ordering;
+use core::cmp::PartialOrd;
+use core::fmt::Debug;
+use core::fmt::Display;
+use core::fmt::Write;
+use core::ops::{Add, Div, Sub};
 use num_traits::Bounded;
 use num_traits::ToPrimitive;
-use std::cmp::PartialOrd;
-use std::fmt::Debug;
-use std::fmt::Display;
-use std::ops::{Add, Div};
+use num_traits::{PrimInt, Unsigned, WrappingAdd};
+use std::collections::VecDeque;
 
-#[derive(Debug, PartialEq)]
+mod atomic_rate;
+mod byte_rate;
+mod counter;
+mod counter_rate;
+mod decaying_aggregate;
+mod duration_aggregate;
+mod error;
+mod event_rate;
+mod ewma;
+mod fixed_aggregate;
+mod gauge;
+#[cfg(feature = "graphite")]
+pub mod graphite;
+mod histogram;
+#[cfg(feature = "influx")]
+pub mod influx;
+mod multi_rate;
+#[cfg(feature = "openmetrics")]
+pub mod openmetrics;
+mod p2_quantile;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+mod ratio;
+mod registry;
+mod reservoir;
+#[cfg(feature = "serde")]
+mod serde_time;
+mod sliding_aggregate;
+#[cfg(feature = "statsd")]
+pub mod statsd;
+#[cfg(feature = "tdigest")]
+mod tdigest;
+mod throughput;
+mod timer;
+
+pub use atomic_rate::AtomicRateMetric;
+pub use byte_rate::{BytePrefix, ByteRate};
+pub use counter::Counter;
+pub use counter_rate::CounterRate;
+pub use decaying_aggregate::DecayingAggregate;
+pub use duration_aggregate::DurationAggregate;
+pub use error::MetricError;
+pub use event_rate::EventRate;
+pub use ewma::EwmaMetric;
+pub use fixed_aggregate::FixedAggregate;
+pub use gauge::Gauge;
+pub use histogram::Histogram;
+pub use multi_rate::MultiRateMetric;
+pub use p2_quantile::P2Quantile;
+pub use ratio::RatioMetric;
+pub use registry::MetricRegistry;
+pub use reservoir::Reservoir;
+pub use sliding_aggregate::SlidingAggregate;
+#[cfg(feature = "tdigest")]
+pub use tdigest::TDigest;
+pub use throughput::ThroughputMetric;
+pub use timer::Timer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MinMaxAvg<T: Display> {
     pub min: T,
     pub avg: f32,
     pub max: T,
+    /// The unit is serialized, but since it is a `&'static str` it can not be deserialized
+    /// and is left empty. Use [`MinMaxAvg::with_unit`] to attach it again.
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
     pub unit: &'static str,
 }
 
@@ -33,25 +99,255 @@ impl<T: Display> MinMaxAvg<T> {
         self.unit = unit;
         self
     }
+
+    /// Returns the values for display with the given number of decimals for floating
+    /// point values.
+    pub fn with_precision(self, precision: usize) -> PreciseMinMaxAvg<T> {
+        PreciseMinMaxAvg {
+            values: self,
+            precision,
+        }
+    }
+
+    fn format_text(&self, precision: Option<usize>) -> String {
+        match precision {
+            Some(precision) => format!(
+                "min:{:.*}{}, avg:{:.*}{}, max:{:.*}{}",
+                precision,
+                self.min,
+                self.unit,
+                precision,
+                self.avg,
+                self.unit,
+                precision,
+                self.max,
+                self.unit,
+            ),
+            None => format!(
+                "min:{}{}, avg:{}{}, max:{}{}",
+                self.min, self.unit, self.avg, self.unit, self.max, self.unit,
+            ),
+        }
+    }
 }
 
-impl<T: Display> Display for MinMaxAvg<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// A [`MinMaxAvg`] that is displayed with a fixed number of decimals, returned by
+/// [`MinMaxAvg::with_precision`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreciseMinMaxAvg<T: Display> {
+    pub values: MinMaxAvg<T>,
+    /// The number of decimals used for floating point values.
+    pub precision: usize,
+}
+
+impl<T: Display + Copy + Sub<Output = T>> PreciseMinMaxAvg<T> {
+    /// Formats only the selected values with the precision, see
+    /// [`MinMaxAvg::format_parts`].
+    pub fn format_parts(&self, parts: &[MetricPart]) -> String {
+        self.values.format_parts_with(parts, Some(self.precision))
+    }
+}
+
+/// Honors the width, fill and alignment of the formatter, like [`MinMaxAvg`]. The precision
+/// of the formatter is ignored in favor of [`PreciseMinMaxAvg::precision`].
+impl<T: Display> Display for PreciseMinMaxAvg<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        pad_to_width(f, &self.values.format_text(Some(self.precision)))
+    }
+}
+
+impl<T: Display + Copy> MinMaxAvg<T> {
+    /// Returns the values as a `(min, avg, max)` tuple, without the unit.
+    pub fn as_tuple(&self) -> (T, f32, T) {
+        (self.min, self.avg, self.max)
+    }
+}
+
+impl<T: Display> From<MinMaxAvg<T>> for (T, f32, T) {
+    fn from(values: MinMaxAvg<T>) -> Self {
+        (values.min, values.avg, values.max)
+    }
+}
+
+impl<T: Display + Copy + Sub<Output = T>> MinMaxAvg<T> {
+    /// Returns the span of the window, `max - min`.
+    pub fn range(&self) -> T {
+        self.max - self.min
+    }
+
+    /// Formats the values like [`Display`], followed by the [range](MinMaxAvg::range).
+    pub fn to_string_with_range(&self) -> String {
+        format!("{}, range:{}{}", self, self.range(), self.unit)
+    }
+
+    /// Formats only the selected values, in the order given, e.g. `avg:2.5ms` for
+    /// `[MetricPart::Avg, MetricPart::Unit]` or `min:1, max:4` for
+    /// `[MetricPart::Min, MetricPart::Max]`.
+    ///
+    /// The unit is only written after each value if [`MetricPart::Unit`] is one of the
+    /// parts. Use [`PreciseMinMaxAvg::format_parts`] to limit the number of decimals.
+    pub fn format_parts(&self, parts: &[MetricPart]) -> String {
+        self.format_parts_with(parts, None)
+    }
+
+    fn format_parts_with(&self, parts: &[MetricPart], precision: Option<usize>) -> String {
+        let unit = if parts.contains(&MetricPart::Unit) {
+            self.unit
+        } else {
+            ""
+        };
+        let format_value = |value: &dyn Display| match precision {
+            Some(precision) => format!("{value:.precision$}{unit}"),
+            None => format!("{value}{unit}"),
+        };
+        parts
+            .iter()
+            .filter_map(|part| match part {
+                MetricPart::Min => Some(format!("min:{}", format_value(&self.min))),
+                MetricPart::Avg => Some(format!("avg:{}", format_value(&self.avg))),
+                MetricPart::Max => Some(format!("max:{}", format_value(&self.max))),
+                MetricPart::Range => Some(format!("range:{}", format_value(&self.range()))),
+                MetricPart::Unit => None,
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl<T: Display + ToPrimitive> MinMaxAvg<T> {
+    /// Returns the change from `other` to `self`, e.g. from the previous window to this one.
+    ///
+    /// The changes of min and max are calculated as `f64`, so that they can be negative
+    /// also for unsigned values. The unit of `self` is used for the delta. Both values are
+    /// expected to have the same unit, which is checked with a debug assertion.
+    pub fn diff(&self, other: &Self) -> MinMaxAvgDelta {
+        debug_assert_eq!(
+            self.unit, other.unit,
+            "compared values must have the same unit"
+        );
+        let delta = |value: &T, other: &T| {
+            value.to_f64().unwrap_or(f64::NAN) - other.to_f64().unwrap_or(f64::NAN)
+        };
+        MinMaxAvgDelta {
+            min: delta(&self.min, &other.min),
+            avg: self.avg - other.avg,
+            max: delta(&self.max, &other.max),
+            unit: self.unit,
+        }
+    }
+}
+
+/// A part of a [`MinMaxAvg`] to include in [`MinMaxAvg::format_parts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricPart {
+    Min,
+    Avg,
+    Max,
+    /// The span of the window, see [`MinMaxAvg::range`].
+    Range,
+    /// Writes the unit after each of the other parts.
+    Unit,
+}
+
+/// The change between two [`MinMaxAvg`], returned by [`MinMaxAvg::diff`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinMaxAvgDelta {
+    pub min: f64,
+    pub avg: f32,
+    pub max: f64,
+    pub unit: &'static str,
+}
+
+/// Displays each change with an explicit sign, e.g. `min:+1ms, avg:+2.5ms, max:-3ms`.
+impl Display for MinMaxAvgDelta {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
-            "min:{}{}, avg:{}{}, max:{}{}",
+            "min:{:+}{}, avg:{:+}{}, max:{:+}{}",
             self.min, self.unit, self.avg, self.unit, self.max, self.unit,
         )
     }
 }
 
+/// Orders by `avg` first, so that a list of results can be sorted to find the worst
+/// offenders. Ties are broken by `max` and then by `min`, and finally by `unit` to stay
+/// consistent with [`PartialEq`].
+///
+/// `Ord` is not implemented since `avg` is an `f32`, so sort with
+/// `values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))`.
+impl<T: Display + PartialOrd> PartialOrd for MinMaxAvg<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.avg.partial_cmp(&other.avg)? {
+            Ordering::Equal => {}
+            ordering => return Some(ordering),
+        }
+        match self.max.partial_cmp(&other.max)? {
+            Ordering::Equal => {}
+            ordering => return Some(ordering),
+        }
+        match self.min.partial_cmp(&other.min)? {
+            Ordering::Equal => {}
+            ordering => return Some(ordering),
+        }
+        Some(self.unit.cmp(other.unit))
+    }
+}
+
+/// Honors the width, fill and alignment of the formatter, e.g. `format!("{:>40}", values)`,
+/// by padding the complete text. A precision, e.g. `format!("{:.2}", values)`, sets the
+/// number of decimals of each value, like [`MinMaxAvg::with_precision`].
+impl<T: Display> Display for MinMaxAvg<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        pad_to_width(f, &self.format_text(f.precision()))
+    }
+}
+
+/// Writes `text` padded to the width of the formatter, using its fill and alignment.
+///
+/// Unlike [`core::fmt::Formatter::pad`], the precision of the formatter does not truncate
+/// the text.
+fn pad_to_width(f: &mut core::fmt::Formatter<'_>, text: &str) -> core::fmt::Result {
+    let length = text.chars().count();
+    let padding = f.width().map_or(0, |width| width.saturating_sub(length));
+    let (before, after) = match f.align() {
+        Some(core::fmt::Alignment::Right) => (padding, 0),
+        Some(core::fmt::Alignment::Center) => (padding / 2, padding - padding / 2),
+        Some(core::fmt::Alignment::Left) | None => (0, padding),
+    };
+    let fill = f.fill();
+    for _ in 0..before {
+        f.write_char(fill)?;
+    }
+    f.write_str(text)?;
+    for _ in 0..after {
+        f.write_char(fill)?;
+    }
+    Ok(())
+}
+
+/// The shortest partial interval that [`RateMetric::estimated_rate`] will base an estimate on.
+pub const MIN_ESTIMATE_ELAPSED: MillisDuration = MillisDuration::from_millis(10);
+
 /// Evaluating how many times something occurs every second.
+///
+/// The events are counted with `C`, which defaults to `u32`. Use a wider type, e.g. `u64`
+/// for byte counts, with [`RateMetricBuilder::build_with_count`].
 #[derive(Debug)]
-pub struct RateMetric {
-    count: u32,
+pub struct RateMetric<C = u32> {
+    count: C,
+    total: u64,
+    weighted_count: f32,
     last_calculated_at: Millis,
     average: f32,
+    peak: f32,
+    min: f32,
+    alpha: f32,
     measurement_interval: MillisDuration,
+    unit: &'static str,
+    saturating: bool,
+    clamp: Option<(f32, f32)>,
+    fixed_windows: bool,
+    jitter: Option<JitterState>,
 }
 
 impl RateMetric {
@@ -65,38 +361,215 @@ impl RateMetric {
     ///
     /// A `RateMetric` instance with an initialized count and time.
     pub fn new(time: Millis) -> Self {
-        Self {
-            count: 0,
-            last_calculated_at: time,
-            measurement_interval: MillisDuration::from_millis(500),
-            average: 0.0,
-        }
+        RateMetricBuilder::new().build_unchecked(time)
     }
 
-    pub fn with_interval(time: Millis, measurement_interval: f32) -> Self {
-        Self {
-            count: 0,
-            last_calculated_at: time,
-            measurement_interval: MillisDuration::from_secs(measurement_interval)
-                .expect("measurement interval should be positive"),
-            average: 0.0,
-        }
+    /// Creates a new `RateMetric` with a custom measurement interval.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The initial [`Millis`] from which time tracking starts.
+    /// * `measurement_interval` - The measurement interval in seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MetricError::InvalidInterval`] if the interval is not at least one
+    /// millisecond.
+    pub fn with_interval(time: Millis, measurement_interval: f32) -> Result<Self, MetricError> {
+        Ok(RateMetricBuilder::new()
+            .interval(interval_from_secs(measurement_interval)?)
+            .build_unchecked(time))
+    }
+
+    /// Creates a new `RateMetric` whose pending count saturates at `u32::MAX` instead of
+    /// wrapping around, see [`RateMetric::add`].
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The initial [`Millis`] from which time tracking starts.
+    pub fn new_saturating(time: Millis) -> Self {
+        RateMetricBuilder::new()
+            .saturating(true)
+            .build_unchecked(time)
+    }
+
+    /// Creates a new `RateMetric` that also tracks the spacing between the events counted
+    /// with [`RateMetric::increment_at`], see [`RateMetric::jitter`].
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The initial [`Millis`] from which time tracking starts.
+    pub fn new_with_jitter(time: Millis) -> Self {
+        RateMetricBuilder::new().jitter(true).build_unchecked(time)
+    }
+
+    /// Returns a [`RateMetricBuilder`] for configuring a new `RateMetric`.
+    pub const fn builder() -> RateMetricBuilder {
+        RateMetricBuilder::new()
+    }
+
+    /// Creates a new `RateMetric` that smooths the rate using an exponentially weighted
+    /// moving average.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The initial [`Millis`] from which time tracking starts.
+    /// * `measurement_interval` - The measurement interval in seconds.
+    /// * `alpha` - The weight of each new rate, in the range `0.0..=1.0`. A value of `1.0`
+    ///   disables smoothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the interval is not at least one millisecond or if `alpha` is
+    /// outside of `0.0..=1.0`.
+    pub fn with_smoothing(
+        time: Millis,
+        measurement_interval: f32,
+        alpha: f32,
+    ) -> Result<Self, MetricError> {
+        RateMetricBuilder::new()
+            .interval(interval_from_secs(measurement_interval)?)
+            .smoothing(alpha)
+            .build(time)
+    }
+}
+
+fn interval_from_secs(seconds: f32) -> Result<MillisDuration, MetricError> {
+    match MillisDuration::from_secs(seconds) {
+        Ok(interval) if interval.as_millis() > 0 => Ok(interval),
+        _ => Err(MetricError::InvalidInterval),
+    }
+}
+
+impl<C> RateMetric<C>
+where
+    C: PrimInt + Unsigned + WrappingAdd + Debug,
+{
+    /// Sets the unit that is displayed in front of `/s`, e.g. `pkt` for `pkt/s`.
+    pub fn with_unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Returns the unit that is displayed in front of `/s`, or an empty string if no unit
+    /// has been set.
+    pub fn unit(&self) -> &'static str {
+        self.unit
+    }
+
+    /// Clamps every calculated rate into `min..=max` before it is stored, so that a
+    /// momentary spike, e.g. from a very short elapsed time, can't produce an absurd value.
+    ///
+    /// Only the reported rate is affected, the events are still counted as usual. `min`
+    /// must not be greater than `max`, and neither may be NaN, which is checked with a
+    /// debug assertion.
+    pub fn with_clamp(mut self, min: f32, max: f32) -> Self {
+        debug_assert!(
+            min <= max,
+            "clamp bounds must be ordered, got {min}..={max}"
+        );
+        self.clamp = Some((min, max));
+        self
+    }
+
+    /// Aligns the measurement intervals to a fixed grid, starting at the construction time.
+    ///
+    /// By default, [`RateMetric::update`] calculates the rate over the whole elapsed time and
+    /// starts the next interval at the time of the update. With fixed windows, the start
+    /// of the next interval is instead advanced by the whole number of intervals that have
+    /// elapsed, and the rate is calculated over those intervals. The remainder counts
+    /// toward the next interval, so that irregular update calls don't shift the windows.
+    ///
+    /// Events are counted toward the interval in which `update` closes them.
+    pub fn with_fixed_windows(mut self) -> Self {
+        self.fixed_windows = true;
+        self
     }
 
     /// Increments the internal event count by one.
     ///
     /// Call this method each time an event occurs that you want to track.
     pub fn increment(&mut self) {
-        self.count += 1;
+        self.add(C::one());
+    }
+
+    /// Increments the internal event count by one, like [`RateMetric::increment`], and
+    /// records the time of the event if the metric tracks [jitter](RateMetric::jitter).
+    ///
+    /// An event that is earlier than the previous one is counted, but not used for the
+    /// jitter.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The [`Millis`] at which the event occurred.
+    pub fn increment_at(&mut self, now: Millis) {
+        self.increment();
+        if let Some(jitter) = &mut self.jitter {
+            jitter.record(now);
+        }
+    }
+
+    /// Returns the standard deviation of the time between the events counted with
+    /// [`RateMetric::increment_at`].
+    ///
+    /// Returns `None` if the metric was not created with jitter tracking, e.g. with
+    /// [`RateMetric::new_with_jitter`], or if fewer than three events have been recorded.
+    pub fn jitter(&self) -> Option<MillisDuration> {
+        self.jitter.as_ref().and_then(JitterState::deviation)
     }
 
     /// Adds a specified number of events to the internal count.
     ///
+    /// If more events than the maximum of `C` are added within one measurement interval,
+    /// the pending count wraps around by default. A metric created with
+    /// [`RateMetric::new_saturating`], or with [`RateMetricBuilder::saturating`], caps the
+    /// pending count at the maximum instead. The lifetime [total](RateMetric::total) always
+    /// saturates.
+    ///
     /// # Arguments
     ///
     /// * `count` - The number of events to add.
-    pub fn add(&mut self, count: u32) {
-        self.count += count;
+    pub fn add(&mut self, count: C) {
+        self.count = if self.saturating {
+            self.count.saturating_add(count)
+        } else {
+            self.count.wrapping_add(&count)
+        };
+        self.total = self
+            .total
+            .saturating_add(count.to_u64().unwrap_or(u64::MAX));
+    }
+
+    /// Adds a fractional event weight to the internal count.
+    ///
+    /// Weights are accumulated separately from the integer count and both are included
+    /// when the rate is calculated. Negative, infinite and NaN weights are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `weight` - The weight of the event, e.g. `0.5` for half an occurrence.
+    pub fn add_weighted(&mut self, weight: f32) {
+        if weight.is_finite() && weight >= 0.0 {
+            self.weighted_count += weight;
+        }
+    }
+
+    /// Decrements the internal event count by one.
+    ///
+    /// The count saturates at zero instead of panicking or wrapping around.
+    pub fn decrement(&mut self) {
+        self.sub(C::one());
+    }
+
+    /// Removes a specified number of events from the internal count.
+    ///
+    /// The count saturates at zero instead of panicking or wrapping around.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of events to remove.
+    pub fn sub(&mut self, count: C) {
+        self.count = self.count.saturating_sub(count);
     }
 
     /// Updates the rate calculation based on the elapsed time since the last calculation.
@@ -108,117 +581,1358 @@ impl RateMetric {
     /// If the elapsed time since the last calculation is less than the measurement interval,
     /// this method returns early without updating the rate.
     pub fn update(&mut self, time: Millis) {
-        let elapsed_time = time - self.last_calculated_at;
+        self.update_checked(time);
+    }
+
+    /// Updates the rate calculation, like [`RateMetric::update`], and calls `f` with the new
+    /// rate if it was recalculated.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The current [`Millis`] representing the time at which the update is triggered.
+    /// * `f` - Called exactly once for every closed measurement interval.
+    pub fn update_with<F: FnMut(f32)>(&mut self, time: Millis, mut f: F) {
+        if self.update_checked(time) {
+            f(self.average);
+        }
+    }
+
+    /// Updates the rate calculation, like [`RateMetric::update`], and reports if a new rate
+    /// was calculated.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The current [`Millis`] representing the time at which the update is triggered.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the measurement interval had elapsed and the rate was recalculated,
+    /// `false` otherwise. A `time` earlier than the last calculation is ignored and also
+    /// returns `false`.
+    pub fn update_checked(&mut self, time: Millis) -> bool {
+        let Some(elapsed_time) = time.checked_duration_since_ms(self.last_calculated_at) else {
+            return false;
+        };
         if elapsed_time < self.measurement_interval {
-            return;
+            return false;
         }
 
-        let rate = self.count as f32 / elapsed_time.as_secs();
+        let interval_millis = self.measurement_interval.as_millis();
+        let (window, next_start) = if self.fixed_windows && interval_millis > 0 {
+            let window = MillisDuration::from_millis(
+                elapsed_time.as_millis() / interval_millis * interval_millis,
+            );
+            (window, self.last_calculated_at + window)
+        } else {
+            (elapsed_time, time)
+        };
+        self.close_interval(window, next_start);
 
-        // Reset the counter and start time for the next period
-        self.count = 0;
-        self.last_calculated_at = time;
-        self.average = rate;
+        true
     }
 
-    pub fn rate(&self) -> f32 {
-        self.average
+    /// Calculates the rate immediately, over the time elapsed since the last calculation,
+    /// even if it is shorter than the measurement interval.
+    ///
+    /// The next measurement interval starts at `time`, also with
+    /// [fixed windows](RateMetric::with_fixed_windows). Nothing happens if no time has
+    /// elapsed, or if `time` is earlier than the last calculation.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The current [`Millis`] representing the time at which the update is triggered.
+    pub fn force_update(&mut self, time: Millis) {
+        match time.checked_duration_since_ms(self.last_calculated_at) {
+            Some(elapsed_time) if elapsed_time.as_millis() > 0 => {
+                self.close_interval(elapsed_time, time);
+            }
+            _ => {}
+        }
     }
-}
 
-/// Tracks minimum, maximum, and average values for numeric data (e.g., `i32`, `u32`, `f32`).
-#[derive(Debug)]
-pub struct AggregateMetric<T> {
-    sum: T,
-    count: u8,
-    max: T,
-    min: T,
-    threshold: u8,
-    max_ack: T,
-    min_ack: T,
-    avg: f32,
-    avg_is_set: bool,
-    unit: &'static str,
-}
+    /// Calculates the rate of the pending events over `window` and starts the next
+    /// measurement interval at `next_start`.
+    fn close_interval(&mut self, window: MillisDuration, next_start: Millis) {
+        let mut rate = self.total_pending() / window.as_secs();
+        if let Some((min, max)) = self.clamp {
+            rate = rate.max(min).min(max);
+        }
 
-impl<T> AggregateMetric<T>
-where
-    T: Add<Output = T>
-        + Div<Output = T>
-        + Copy
-        + PartialOrd
-        + Default
-        + From<u8>
-        + Debug
-        + Display
-        + Bounded
-        + ToPrimitive,
-{
-    /// Creates a new `AggregateMetric` instance with a given threshold.
-    pub fn new(threshold: u8) -> Result<Self, String> {
-        if threshold == 0 {
-            Err("threshold can not be zero".to_string())
-        } else {
-            Ok(Self {
-                sum: T::default(),
-                count: 0,
-                max: T::default(),
-                min: T::default(),
-                threshold,
-                max_ack: T::min_value(),
-                min_ack: T::max_value(),
-                avg: 0.0,
-                avg_is_set: false,
-                unit: "",
-            })
+        // Reset the counter and start time for the next period
+        self.count = C::zero();
+        self.weighted_count = 0.0;
+        self.last_calculated_at = next_start;
+        self.average = self.alpha * rate + (1.0 - self.alpha) * self.average;
+        if self.average > self.peak {
+            self.peak = self.average;
+        }
+        if self.average > 0.0 && (self.min == 0.0 || self.average < self.min) {
+            self.min = self.average;
         }
     }
 
-    pub fn with_unit(mut self, unit: &'static str) -> Self {
-        self.unit = unit;
-        self
+    pub fn rate(&self) -> f32 {
+        self.average
     }
 
-    /// Calculates the mean value, returning `None` if no values have been added.
-    pub fn average(&self) -> Option<f32> {
-        if self.avg_is_set {
-            Some(self.avg)
-        } else {
-            None
+    /// Estimates the rate of the measurement interval that is still in progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current [`Millis`] used to compute the partial elapsed time.
+    ///
+    /// # Returns
+    ///
+    /// The pending count divided by the partial elapsed time. If less than
+    /// [`MIN_ESTIMATE_ELAPSED`] has elapsed, the last calculated rate is returned instead, so
+    /// that a tiny elapsed time doesn't produce a huge estimate.
+    pub fn estimated_rate(&self, now: Millis) -> f32 {
+        match now.checked_duration_since_ms(self.last_calculated_at) {
+            Some(elapsed) if elapsed >= MIN_ESTIMATE_ELAPSED => {
+                self.total_pending() / elapsed.as_secs()
+            }
+            _ => self.average,
         }
     }
 
-    /// Adds a value of type `T` to the metric.
-    pub fn add(&mut self, value: T) {
-        self.sum = self.sum + value;
-        self.count += 1;
-
-        // Update the max and min acknowledgments
-        if value > self.max_ack {
-            self.max_ack = value;
-        }
-        if value < self.min_ack {
-            self.min_ack = value;
-        }
-
-        // Check if the threshold is reached to calculate stats and reset counters
-        if self.count >= self.threshold {
-            let sum_f32 = self.sum.to_f32().unwrap_or(0.0);
-            let avg_f32 = sum_f32 / self.count as f32;
+    /// Returns the time at which the last measurement interval closed, or the construction
+    /// time if no interval has closed yet.
+    pub fn last_update(&self) -> Millis {
+        self.last_calculated_at
+    }
 
-            self.avg = avg_f32;
+    /// Returns `true` if the last measurement interval closed more than `max_age` before
+    /// `now`, e.g. because [`RateMetric::update`] is no longer called.
+    pub fn is_stale(&self, now: Millis, max_age: MillisDuration) -> bool {
+        now.checked_duration_since_ms(self.last_calculated_at)
+            .is_some_and(|age| age > max_age)
+    }
 
-            self.min = self.min_ack;
-            self.max = self.max_ack;
-            self.max_ack = T::min_value();
-            self.min_ack = T::max_value();
-            self.count = 0;
-            self.avg_is_set = true;
-            self.sum = T::default();
-        }
+    /// Returns the duration that must elapse before a new rate is calculated.
+    pub fn interval(&self) -> MillisDuration {
+        self.measurement_interval
     }
 
-    /// Returns the minimum, average, and maximum values as a tuple, if available.
+    /// Changes the measurement interval.
+    ///
+    /// Events counted so far are kept, and the new interval is applied the next time
+    /// [`RateMetric::update`] is called.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MetricError::InvalidInterval`] if the interval is zero. The interval is
+    /// left unchanged.
+    pub fn set_interval(&mut self, interval: MillisDuration) -> Result<(), MetricError> {
+        if interval.as_millis() == 0 {
+            return Err(MetricError::InvalidInterval);
+        }
+        self.measurement_interval = interval;
+        Ok(())
+    }
+
+    /// Returns the number of events counted since the last rate calculation.
+    pub fn pending_count(&self) -> C {
+        self.count
+    }
+
+    /// Returns the number of events added by [`RateMetric::increment`] and
+    /// [`RateMetric::add`] over the lifetime of the metric.
+    ///
+    /// The total is not affected by measurement interval resets, [`RateMetric::sub`] or
+    /// weighted events.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    fn total_pending(&self) -> f32 {
+        self.count.to_f32().unwrap_or(f32::MAX) + self.weighted_count
+    }
+
+    /// Returns the rate scaled to events per `duration` instead of per second, e.g. events
+    /// per frame for a 60 Hz game loop.
+    pub fn rate_per(&self, duration: MillisDuration) -> f32 {
+        self.average * duration.as_secs()
+    }
+
+    /// Returns the highest rate calculated by [`RateMetric::update`] so far.
+    ///
+    /// The peak is kept across measurement intervals and is only cleared by
+    /// [`RateMetric::reset_peak`] or [`RateMetric::reset_rate_bounds`].
+    pub fn peak_rate(&self) -> f32 {
+        self.peak
+    }
+
+    /// Clears the tracked peak rate back to `0.0`.
+    pub fn reset_peak(&mut self) {
+        self.peak = 0.0;
+    }
+
+    /// Returns the lowest nonzero rate calculated by [`RateMetric::update`] so far, or `0.0`
+    /// if no nonzero rate has been calculated.
+    ///
+    /// Together with [`RateMetric::peak_rate`] this is the envelope of the observed rates.
+    /// It is only cleared by [`RateMetric::reset_rate_bounds`].
+    pub fn min_rate(&self) -> f32 {
+        self.min
+    }
+
+    /// Clears both the tracked peak rate and the tracked minimum rate back to `0.0`.
+    pub fn reset_rate_bounds(&mut self) {
+        self.peak = 0.0;
+        self.min = 0.0;
+    }
+
+    /// Restarts the measurement interval at `time` and discards the pending events, but
+    /// keeps the last calculated rate until the new interval closes.
+    ///
+    /// Useful when the rate would otherwise be skewed by a pause, e.g. during a scene load.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The [`Millis`] at which the new measurement interval starts.
+    pub fn rebase(&mut self, time: Millis) {
+        self.count = C::zero();
+        self.weighted_count = 0.0;
+        self.last_calculated_at = time;
+    }
+
+    /// Restarts the measurement interval at `time`, like [`RateMetric::rebase`], and also
+    /// sets the calculated rate back to `0.0`.
+    ///
+    /// The peak rate and the lifetime [total](RateMetric::total) are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The [`Millis`] at which the new measurement interval starts.
+    pub fn reset(&mut self, time: Millis) {
+        self.rebase(time);
+        self.average = 0.0;
+    }
+
+    /// Captures the complete internal state, e.g. to save it along with the rest of a
+    /// simulation and resume it later with [`RateMetric::from_state`].
+    pub fn to_state(&self) -> RateMetricState<C> {
+        RateMetricState {
+            count: self.count,
+            total: self.total,
+            weighted_count: self.weighted_count,
+            last_calculated_at: self.last_calculated_at,
+            average: self.average,
+            peak: self.peak,
+            min_rate: self.min,
+            alpha: self.alpha,
+            measurement_interval: self.measurement_interval,
+            unit: self.unit,
+            saturating: self.saturating,
+            clamp: self.clamp,
+            fixed_windows: self.fixed_windows,
+            jitter: self.jitter,
+        }
+    }
+
+    /// Restores a `RateMetric` from a state captured by [`RateMetric::to_state`].
+    ///
+    /// The restored metric behaves bit-identically to the one the state was captured from.
+    pub fn from_state(state: RateMetricState<C>) -> Self {
+        Self {
+            count: state.count,
+            total: state.total,
+            weighted_count: state.weighted_count,
+            last_calculated_at: state.last_calculated_at,
+            average: state.average,
+            peak: state.peak,
+            min: state.min_rate,
+            alpha: state.alpha,
+            measurement_interval: state.measurement_interval,
+            unit: state.unit,
+            saturating: state.saturating,
+            clamp: state.clamp,
+            fixed_windows: state.fixed_windows,
+            jitter: state.jitter,
+        }
+    }
+}
+
+/// The complete internal state of a [`RateMetric`], see [`RateMetric::to_state`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateMetricState<C = u32> {
+    /// The number of events counted since the last rate calculation.
+    pub count: C,
+    /// The number of events counted over the lifetime of the metric.
+    pub total: u64,
+    /// The fractional events counted since the last rate calculation.
+    pub weighted_count: f32,
+    /// The time at which the last measurement interval closed.
+    pub last_calculated_at: Millis,
+    /// The last calculated rate.
+    pub average: f32,
+    /// The highest rate calculated so far.
+    pub peak: f32,
+    /// The lowest nonzero rate calculated so far.
+    pub min_rate: f32,
+    /// The weight of each new rate.
+    pub alpha: f32,
+    pub measurement_interval: MillisDuration,
+    pub unit: &'static str,
+    /// If the pending count saturates instead of wrapping around.
+    pub saturating: bool,
+    /// The range that calculated rates are clamped into, see [`RateMetric::with_clamp`].
+    pub clamp: Option<(f32, f32)>,
+    /// If the measurement intervals are aligned, see [`RateMetric::with_fixed_windows`].
+    pub fixed_windows: bool,
+    /// The tracked spacing between events, or `None` if jitter is not tracked.
+    pub jitter: Option<JitterState>,
+}
+
+/// The spacing between the events of a [`RateMetric`], see [`RateMetric::jitter`].
+///
+/// Only the time of the previous event is kept, the inter-arrival times are accumulated
+/// as a running mean and variance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JitterState {
+    /// The time of the previous event, or `None` before the first event.
+    pub last_event_at: Option<Millis>,
+    /// The number of inter-arrival times recorded.
+    pub count: u64,
+    /// The mean inter-arrival time in milliseconds.
+    pub mean: f64,
+    /// The sum of squared differences from the mean, in square milliseconds.
+    pub m2: f64,
+}
+
+impl JitterState {
+    const fn new() -> Self {
+        Self {
+            last_event_at: None,
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    fn record(&mut self, now: Millis) {
+        if let Some(last) = self.last_event_at {
+            let Some(gap) = now.checked_duration_since_ms(last) else {
+                return;
+            };
+            // Welford's online algorithm
+            let gap = gap.as_millis() as f64;
+            self.count += 1;
+            let delta = gap - self.mean;
+            self.mean += delta / self.count as f64;
+            self.m2 += delta * (gap - self.mean);
+        }
+        self.last_event_at = Some(now);
+    }
+
+    fn deviation(&self) -> Option<MillisDuration> {
+        if self.count < 2 {
+            return None;
+        }
+        let deviation = (self.m2 / self.count as f64).sqrt();
+        Some(MillisDuration::from_millis(deviation.round() as u64))
+    }
+}
+
+/// Configures and creates a [`RateMetric`].
+///
+/// ```
+/// use metricator::RateMetric;
+/// use monotonic_time_rs::{Millis, MillisDuration};
+///
+/// let metric = RateMetric::builder()
+///     .interval(MillisDuration::from_millis(1_000))
+///     .smoothing(0.5)
+///     .unit("pkt")
+///     .build(Millis::new(0))
+///     .expect("alpha should be valid");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RateMetricBuilder {
+    interval: MillisDuration,
+    alpha: f32,
+    unit: &'static str,
+    saturating: bool,
+    jitter: bool,
+}
+
+impl RateMetricBuilder {
+    /// Creates a builder with a 500 ms interval, no smoothing, no unit, a wrapping
+    /// pending count and no jitter tracking.
+    pub const fn new() -> Self {
+        Self {
+            interval: MillisDuration::from_millis(500),
+            alpha: 1.0,
+            unit: "",
+            saturating: false,
+            jitter: false,
+        }
+    }
+
+    /// Sets the measurement interval.
+    pub fn interval(mut self, interval: MillisDuration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the weight of each new rate, in the range `0.0..=1.0`, see
+    /// [`RateMetric::with_smoothing`].
+    pub fn smoothing(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the unit that is displayed in front of `/s`.
+    pub fn unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Sets if the pending count saturates at its maximum instead of wrapping around, see
+    /// [`RateMetric::add`].
+    pub fn saturating(mut self, saturating: bool) -> Self {
+        self.saturating = saturating;
+        self
+    }
+
+    /// Sets if the spacing between events is tracked, see [`RateMetric::jitter`].
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Creates the [`RateMetric`].
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The initial [`Millis`] from which time tracking starts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the interval is zero or if the smoothing factor is outside of
+    /// `0.0..=1.0`.
+    pub fn build(self, time: Millis) -> Result<RateMetric, MetricError> {
+        self.build_with_count(time)
+    }
+
+    /// Creates a [`RateMetric`] that counts the events with `C` instead of `u32`, e.g.
+    /// `u64` for byte counts.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The initial [`Millis`] from which time tracking starts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the interval is zero or if the smoothing factor is outside of
+    /// `0.0..=1.0`.
+    pub fn build_with_count<C>(self, time: Millis) -> Result<RateMetric<C>, MetricError>
+    where
+        C: PrimInt + Unsigned + WrappingAdd + Debug,
+    {
+        if self.interval.as_millis() == 0 {
+            return Err(MetricError::InvalidInterval);
+        }
+        if !(0.0..=1.0).contains(&self.alpha) {
+            return Err(MetricError::InvalidAlpha);
+        }
+        Ok(self.build_unchecked(time))
+    }
+
+    fn build_unchecked<C>(self, time: Millis) -> RateMetric<C>
+    where
+        C: PrimInt + Unsigned + WrappingAdd + Debug,
+    {
+        RateMetric {
+            count: C::zero(),
+            total: 0,
+            weighted_count: 0.0,
+            last_calculated_at: time,
+            measurement_interval: self.interval,
+            average: 0.0,
+            peak: 0.0,
+            min: 0.0,
+            alpha: self.alpha,
+            unit: self.unit,
+            saturating: self.saturating,
+            clamp: None,
+            fixed_windows: false,
+            jitter: self.jitter.then(JitterState::new),
+        }
+    }
+}
+
+impl Default for RateMetricBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> Display for RateMetric<C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "rate:{}{}/s", self.average, self.unit)
+    }
+}
+
+/// Tracks minimum, maximum, and average values for numeric data (e.g., `i32`, `u32`, `f32`).
+///
+/// With the `serde` feature, the complete state can be serialized and restored. The unit is
+/// serialized, but is left empty on deserialization and can be attached again with
+/// [`AggregateMetric::with_unit`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AggregateMetric<T: Display> {
+    /// Saturates at the bounds of `T`, so the statistics are calculated from `sum_f64`.
+    sum: T,
+    sum_f64: f64,
+    sum_of_squares: f64,
+    count: u32,
+    max: T,
+    min: T,
+    threshold: u32,
+    max_ack: T,
+    min_ack: T,
+    avg: f32,
+    avg_f64: f64,
+    variance: f32,
+    published_count: u32,
+    published_sum: T,
+    avg_is_set: bool,
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    unit: &'static str,
+    samples: Option<Vec<T>>,
+    published_samples: Vec<T>,
+    time_window: Option<TimeWindow>,
+    mean: Mean,
+    mean_sum: f64,
+    weighted_sum: f64,
+    weight_sum: f64,
+    is_weighted: bool,
+    history: VecDeque<MinMaxAvg<T>>,
+    history_capacity: usize,
+    #[cfg_attr(feature = "serde", serde(with = "serde_time::option_millis"))]
+    last_sample_at: Option<Millis>,
+    /// Counts the calls to [`AggregateMetric::add_at`], created by the first call.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    sample_rate: Option<RateMetric>,
+    last_value: Option<T>,
+    rejected_count: u64,
+}
+
+/// How the average of an [`AggregateMetric`] window is calculated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Mean {
+    Arithmetic,
+    /// Accumulates the sum of the logarithms of the values.
+    Geometric,
+    /// Accumulates the sum of the reciprocals of the values.
+    Harmonic,
+    /// Discards the given fraction of the lowest and highest buffered values.
+    Trimmed(f32),
+}
+
+/// The stats of a completed [`AggregateMetric`] window, returned by
+/// [`AggregateMetric::snapshot`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AggregateSnapshot<T> {
+    pub min: T,
+    pub avg: f32,
+    pub max: T,
+    /// The number of values in the window.
+    pub count: u32,
+    /// The sum of the values in the window, saturated at the bounds of `T`.
+    pub sum: T,
+    /// The unit is serialized, but is left empty on deserialization.
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    pub unit: &'static str,
+}
+
+/// The percentiles of a completed [`AggregateMetric`] window, returned by
+/// [`AggregateMetric::summary`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantileSummary<T> {
+    pub p50: T,
+    pub p90: T,
+    pub p99: T,
+    pub unit: &'static str,
+}
+
+/// Displays the percentiles, e.g. `p50:2ms p90:7ms p99:15ms`.
+impl<T: Display> Display for QuantileSummary<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "p50:{}{} p90:{}{} p99:{}{}",
+            self.p50, self.unit, self.p90, self.unit, self.p99, self.unit,
+        )
+    }
+}
+
+/// The duration and start time of the window in progress of a time based [`AggregateMetric`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct TimeWindow {
+    #[cfg_attr(feature = "serde", serde(with = "serde_time::millis_duration"))]
+    duration: MillisDuration,
+    #[cfg_attr(feature = "serde", serde(with = "serde_time::millis"))]
+    started_at: Millis,
+}
+
+impl<T> AggregateMetric<T>
+where
+    T: Add<Output = T>
+        + Sub<Output = T>
+        + Div<Output = T>
+        + Copy
+        + PartialOrd
+        + Default
+        + From<u8>
+        + Debug
+        + Display
+        + Bounded
+        + ToPrimitive,
+{
+    /// Creates a new `AggregateMetric` instance with a given threshold.
+    pub fn new(threshold: u32) -> Result<Self, MetricError> {
+        if threshold == 0 {
+            Err(MetricError::ZeroThreshold)
+        } else {
+            Ok(Self {
+                sum: T::default(),
+                sum_f64: 0.0,
+                sum_of_squares: 0.0,
+                count: 0,
+                max: T::default(),
+                min: T::default(),
+                threshold,
+                max_ack: T::min_value(),
+                min_ack: T::max_value(),
+                avg: 0.0,
+                avg_f64: 0.0,
+                variance: 0.0,
+                published_count: 0,
+                published_sum: T::default(),
+                avg_is_set: false,
+                unit: "",
+                samples: None,
+                published_samples: Vec::new(),
+                time_window: None,
+                mean: Mean::Arithmetic,
+                mean_sum: 0.0,
+                weighted_sum: 0.0,
+                weight_sum: 0.0,
+                is_weighted: false,
+                history: VecDeque::new(),
+                history_capacity: 0,
+                last_sample_at: None,
+                sample_rate: None,
+                last_value: None,
+                rejected_count: 0,
+            })
+        }
+    }
+
+    /// Creates a new `AggregateMetric` that can calculate the [mode](AggregateMetric::mode).
+    ///
+    /// Same as [`AggregateMetric::new_buffered`].
+    pub fn new_with_mode(threshold: u32) -> Result<Self, MetricError> {
+        Self::new_buffered(threshold)
+    }
+
+    /// Creates a new `AggregateMetric` that reports the geometric mean as the average,
+    /// which suits ratios and other multiplicative quantities.
+    ///
+    /// Since the logarithm is only defined for positive numbers, zero and negative values
+    /// are skipped by [`AggregateMetric::add`] and rejected by [`AggregateMetric::try_add`].
+    pub fn new_geometric(threshold: u32) -> Result<Self, MetricError> {
+        let mut metric = Self::new(threshold)?;
+        metric.mean = Mean::Geometric;
+        Ok(metric)
+    }
+
+    /// Creates a new `AggregateMetric` that reports the harmonic mean as the average,
+    /// which is the correct mean for rates such as throughput.
+    ///
+    /// To avoid division by zero, zero values are skipped by [`AggregateMetric::add`] and
+    /// rejected by [`AggregateMetric::try_add`].
+    pub fn new_harmonic(threshold: u32) -> Result<Self, MetricError> {
+        let mut metric = Self::new(threshold)?;
+        metric.mean = Mean::Harmonic;
+        Ok(metric)
+    }
+
+    /// Creates a new `AggregateMetric` that reports a trimmed mean as the average, to reject
+    /// outliers.
+    ///
+    /// The values of each window are buffered, like for [`AggregateMetric::new_buffered`],
+    /// and `trim_fraction` of the lowest and of the highest values are discarded before
+    /// averaging. Min and max still report the untrimmed extremes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the threshold is zero or if `trim_fraction` is outside of
+    /// `0.0..0.5`.
+    pub fn new_trimmed(threshold: u32, trim_fraction: f32) -> Result<Self, MetricError> {
+        if !(0.0..0.5).contains(&trim_fraction) {
+            return Err(MetricError::InvalidTrimFraction);
+        }
+        let mut metric = Self::new_buffered(threshold)?;
+        metric.mean = Mean::Trimmed(trim_fraction);
+        Ok(metric)
+    }
+
+    /// Creates a new `AggregateMetric` with the given threshold and adds all values of
+    /// the iterator to it.
+    pub fn from_iter_with_threshold<I: IntoIterator<Item = T>>(
+        iter: I,
+        threshold: u32,
+    ) -> Result<Self, MetricError> {
+        let mut metric = Self::new(threshold)?;
+        metric.extend(iter);
+        Ok(metric)
+    }
+
+    /// Creates a new `AggregateMetric` that publishes a window when a duration has elapsed,
+    /// instead of when a number of values have been added.
+    ///
+    /// Values should be added with [`AggregateMetric::add_at`]. The
+    /// [threshold](AggregateMetric::threshold) is set to `u32::MAX` and only acts as a
+    /// safety limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The [`Millis`] at which the first window starts.
+    /// * `window` - The duration of each window.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MetricError::InvalidInterval`] if the window is zero.
+    pub fn with_time_window(now: Millis, window: MillisDuration) -> Result<Self, MetricError> {
+        if window.as_millis() == 0 {
+            return Err(MetricError::InvalidInterval);
+        }
+        let mut metric = Self::new(u32::MAX)?;
+        metric.time_window = Some(TimeWindow {
+            duration: window,
+            started_at: now,
+        });
+        Ok(metric)
+    }
+
+    /// Creates a new `AggregateMetric` that also retains the values of each window, so
+    /// that the [median](AggregateMetric::median) and
+    /// [percentiles](AggregateMetric::percentile) can be calculated.
+    pub fn new_buffered(threshold: u32) -> Result<Self, MetricError> {
+        let mut metric = Self::new(threshold)?;
+        metric.samples = Some(Vec::with_capacity(threshold as usize));
+        Ok(metric)
+    }
+
+    /// Creates a new `AggregateMetric` that also retains the values of the last `capacity`
+    /// completed windows, see [`AggregateMetric::history`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the threshold or the capacity is zero.
+    pub fn new_with_history(threshold: u32, capacity: usize) -> Result<Self, MetricError> {
+        if capacity == 0 {
+            return Err(MetricError::ZeroCapacity);
+        }
+        let mut metric = Self::new(threshold)?;
+        metric.history = VecDeque::with_capacity(capacity);
+        metric.history_capacity = capacity;
+        Ok(metric)
+    }
+
+    /// Creates a new `AggregateMetric` that can calculate the [median](AggregateMetric::median).
+    ///
+    /// Same as [`AggregateMetric::new_buffered`].
+    pub fn new_with_median(threshold: u32) -> Result<Self, MetricError> {
+        Self::new_buffered(threshold)
+    }
+
+    pub fn with_unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Calculates the mean value, returning `None` if no values have been added.
+    pub fn average(&self) -> Option<f32> {
+        if self.avg_is_set {
+            Some(self.avg)
+        } else {
+            None
+        }
+    }
+
+    /// Calculates the mean value with `f64` precision, returning `None` if no values have
+    /// been added.
+    ///
+    /// Unlike [`AggregateMetric::average`], this stays exact for integer averages above
+    /// 2^24, e.g. byte counts or nanoseconds.
+    pub fn average_f64(&self) -> Option<f64> {
+        self.avg_is_set.then_some(self.avg_f64)
+    }
+
+    /// Returns where the most recently added value falls between the minimum and the
+    /// maximum of its window, from `0.0` at the minimum to `1.0` at the maximum.
+    ///
+    /// The window is the window in progress, or the last completed window if the value
+    /// completed it. If all values of the window are equal, `0.5` is returned. Returns
+    /// `None` if no value has been added since the metric was created or
+    /// [reset](AggregateMetric::reset).
+    pub fn normalized_last(&self) -> Option<f32> {
+        let last = self.last_value?.to_f64()?;
+        let (min, max) = if self.count > 0 {
+            (self.min_ack, self.max_ack)
+        } else {
+            (self.min, self.max)
+        };
+        let (min, max) = (min.to_f64()?, max.to_f64()?);
+        if max <= min {
+            return Some(0.5);
+        }
+        Some(((last - min) / (max - min)) as f32)
+    }
+
+    /// Returns the number of NaN and infinite values that have been skipped by
+    /// [`AggregateMetric::add`] or rejected by [`AggregateMetric::try_add`], e.g. from a
+    /// faulty sensor.
+    pub fn rejected_count(&self) -> u64 {
+        self.rejected_count
+    }
+
+    /// Returns `true` once the first window has completed, i.e. when
+    /// [`AggregateMetric::values`] returns `Some`.
+    pub fn has_data(&self) -> bool {
+        self.avg_is_set
+    }
+
+    /// Returns the number of values added to the window that is in progress.
+    pub fn pending_count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the minimum value of the window in progress, or `None` if no values have
+    /// been added to it.
+    pub fn current_min(&self) -> Option<T> {
+        (self.count > 0).then_some(self.min_ack)
+    }
+
+    /// Returns the maximum value of the window in progress, or `None` if no values have
+    /// been added to it.
+    pub fn current_max(&self) -> Option<T> {
+        (self.count > 0).then_some(self.max_ack)
+    }
+
+    /// Returns the sum of the values in the window in progress.
+    ///
+    /// Together with [`AggregateMetric::pending_count`] this allows for custom calculations
+    /// over the window. The sum saturates at `T::max_value()` (or `T::min_value()`) instead
+    /// of overflowing, the average and variance are not affected by the saturation.
+    pub fn current_sum(&self) -> T {
+        self.sum
+    }
+
+    /// Returns the number of values that completes a window.
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Changes the number of values that completes a window.
+    ///
+    /// The values of the window in progress are kept and count towards the new threshold.
+    /// If the window in progress already has `threshold` or more values, it is published
+    /// immediately, as a whole.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `threshold` is zero.
+    pub fn set_threshold(&mut self, threshold: u32) -> Result<(), MetricError> {
+        if threshold == 0 {
+            return Err(MetricError::ZeroThreshold);
+        }
+        self.threshold = threshold;
+        if self.count >= threshold {
+            self.publish();
+        }
+        Ok(())
+    }
+
+    /// Calculates the population variance of the last completed window, returning `None`
+    /// if no window has completed.
+    pub fn variance(&self) -> Option<f32> {
+        if self.avg_is_set {
+            Some(self.variance)
+        } else {
+            None
+        }
+    }
+
+    /// Calculates the population standard deviation of the last completed window,
+    /// returning `None` if no window has completed.
+    pub fn std_dev(&self) -> Option<f32> {
+        self.variance().map(f32::sqrt)
+    }
+
+    /// Calculates the coefficient of variation, `std_dev / avg`, of the last completed
+    /// window, returning `None` if no window has completed or if the average is zero.
+    pub fn coefficient_of_variation(&self) -> Option<f32> {
+        let avg = self.average()?;
+        if avg == 0.0 {
+            return None;
+        }
+        Some(self.std_dev()? / avg)
+    }
+
+    /// Calculates the median of the last completed window, returning `None` if no window
+    /// has completed or if the metric wasn't created with [`AggregateMetric::new_buffered`].
+    ///
+    /// For an even number of values, the two middle values are interpolated.
+    pub fn median(&self) -> Option<f32> {
+        self.samples.as_ref()?;
+        if !self.avg_is_set || self.published_samples.is_empty() {
+            return None;
+        }
+
+        let len = self.published_samples.len();
+        let upper = self.published_samples[len / 2].to_f64()?;
+        if len % 2 == 1 {
+            Some(upper as f32)
+        } else {
+            let lower = self.published_samples[len / 2 - 1].to_f64()?;
+            Some(((lower + upper) / 2.0) as f32)
+        }
+    }
+
+    /// Returns the most frequently occurring value of the last completed window, or `None`
+    /// if no window has completed or if the metric wasn't created with
+    /// [`AggregateMetric::new_buffered`].
+    ///
+    /// If several values occur equally often, the smallest of them is returned.
+    pub fn mode(&self) -> Option<T> {
+        self.samples.as_ref()?;
+        let mut mode = *self.published_samples.first()?;
+        if !self.avg_is_set {
+            return None;
+        }
+
+        // The published samples are sorted, so equal values are next to each other
+        let mut mode_count = 0;
+        let mut run_start = 0;
+        for index in 1..=self.published_samples.len() {
+            let run_ended = index == self.published_samples.len()
+                || self.published_samples[index] != self.published_samples[run_start];
+            if run_ended {
+                let run_count = index - run_start;
+                if run_count > mode_count {
+                    mode = self.published_samples[run_start];
+                    mode_count = run_count;
+                }
+                run_start = index;
+            }
+        }
+        Some(mode)
+    }
+
+    /// Returns the `q`th percentile of the last completed window using the nearest-rank
+    /// method.
+    ///
+    /// Returns `None` if `q` is outside of `0.0..=100.0`, if no window has completed, or if
+    /// the metric wasn't created with [`AggregateMetric::new_buffered`].
+    pub fn percentile(&self, q: f32) -> Option<T> {
+        if !(0.0..=100.0).contains(&q) {
+            return None;
+        }
+        self.samples.as_ref()?;
+        if !self.avg_is_set || self.published_samples.is_empty() {
+            return None;
+        }
+
+        let len = self.published_samples.len();
+        let rank = ((q / 100.0) * len as f32).ceil() as usize;
+        Some(self.published_samples[rank.clamp(1, len) - 1])
+    }
+
+    /// Returns the 50th, 90th and 99th [percentiles](AggregateMetric::percentile) of the
+    /// last completed window at once.
+    ///
+    /// Returns `None` if no window has completed, or if the metric wasn't created with
+    /// [`AggregateMetric::new_buffered`].
+    pub fn summary(&self) -> Option<QuantileSummary<T>> {
+        Some(QuantileSummary {
+            p50: self.percentile(50.0)?,
+            p90: self.percentile(90.0)?,
+            p99: self.percentile(99.0)?,
+            unit: self.unit,
+        })
+    }
+
+    /// Adds a value of type `T` to the metric.
+    ///
+    /// If adding the value would overflow the sum of the current window, the sum saturates
+    /// at `T::max_value()` (or `T::min_value()`) instead of wrapping around. Use
+    /// [`AggregateMetric::try_add`] to detect the overflow.
+    ///
+    /// Non-finite values, such as `f32::NAN` and `f32::INFINITY`, are skipped, since they
+    /// would otherwise poison the average.
+    pub fn add(&mut self, value: T) {
+        self.add_with(value, |_| {});
+    }
+
+    /// Adds a value, like [`AggregateMetric::add`], and calls `f` with the published values
+    /// if the value completed a window.
+    ///
+    /// `f` is called before the accumulators are reset for the next window.
+    pub fn add_with<F: FnMut(&MinMaxAvg<T>)>(&mut self, value: T, f: F) {
+        if self.check_value(value).is_err() {
+            return;
+        }
+        self.sum = self.saturating_sum(value);
+        if self.add_to_window(value, 1.0) {
+            self.publish_with(f);
+        }
+    }
+
+    /// Adds a value with a weight, so that the average of the window becomes the weighted
+    /// mean, `sum(value * weight) / sum(weight)`.
+    ///
+    /// The threshold still counts the number of added values, regardless of their weights,
+    /// and min and max track the unweighted values. Values added with
+    /// [`AggregateMetric::add`] have a weight of `1.0`. Weights only affect the arithmetic
+    /// mean, and negative or non-finite weights are skipped like non-finite values.
+    pub fn add_weighted(&mut self, value: T, weight: f32) {
+        if !weight.is_finite() || weight < 0.0 || self.check_value(value).is_err() {
+            return;
+        }
+        self.is_weighted = true;
+        self.sum = self.saturating_sum(value);
+        if self.add_to_window(value, f64::from(weight)) {
+            self.publish();
+        }
+    }
+
+    /// Adds all values of the slice to the metric, publishing as many windows as the
+    /// values complete.
+    ///
+    /// This is equivalent to calling [`AggregateMetric::add`] for each value.
+    pub fn add_slice(&mut self, values: &[T]) {
+        for &value in values {
+            self.add(value);
+        }
+    }
+
+    /// Adds a value of type `T` to the metric, unless it is non-finite or would overflow
+    /// the sum of the current window.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, and leaves the metric unchanged, if the value is non-finite or if
+    /// the sum would overflow. Non-finite values are counted by
+    /// [`AggregateMetric::rejected_count`].
+    pub fn try_add(&mut self, value: T) -> Result<(), MetricError> {
+        self.check_value(value)?;
+        if self.sum_overflows(value) {
+            return Err(MetricError::Overflow);
+        }
+        self.sum = self.sum + value;
+        if self.add_to_window(value, 1.0) {
+            self.publish();
+        }
+        Ok(())
+    }
+
+    /// Adds a value of type `T` to the metric at the time `now`.
+    ///
+    /// For a metric created with [`AggregateMetric::with_time_window`], the window in
+    /// progress is first published if its duration has elapsed, and `value` becomes part
+    /// of the next window. For a threshold based metric this is the same as
+    /// [`AggregateMetric::add`].
+    ///
+    /// The time is kept as the [last sample time](AggregateMetric::last_sample_time).
+    pub fn add_at(&mut self, now: Millis, value: T) {
+        self.last_sample_at = Some(now);
+        let sample_rate = self.sample_rate.get_or_insert_with(|| {
+            RateMetricBuilder::new()
+                .interval(MillisDuration::from_millis(1_000))
+                .build_unchecked(now)
+        });
+        sample_rate.update(now);
+        sample_rate.increment();
+        if let Some(time_window) = &mut self.time_window {
+            let elapsed = now.checked_duration_since_ms(time_window.started_at);
+            if elapsed.is_some_and(|elapsed| elapsed >= time_window.duration) {
+                time_window.started_at = now;
+                if self.count > 0 {
+                    self.publish();
+                }
+            }
+        }
+        self.add(value);
+    }
+
+    /// Returns the time of the last value added with [`AggregateMetric::add_at`], or `None`
+    /// if no value has been added with it.
+    ///
+    /// Values added without a timestamp, e.g. with [`AggregateMetric::add`], don't change
+    /// the time.
+    pub fn last_sample_time(&self) -> Option<Millis> {
+        self.last_sample_at
+    }
+
+    /// Returns `true` if no value has been added with [`AggregateMetric::add_at`] within
+    /// `max_age` of `now`, or if no value has been added with it at all.
+    ///
+    /// Values added without a timestamp, e.g. with [`AggregateMetric::add`], are not
+    /// considered.
+    pub fn is_stale(&self, now: Millis, max_age: MillisDuration) -> bool {
+        self.last_sample_at.is_none_or(|last_sample_at| {
+            now.checked_duration_since_ms(last_sample_at)
+                .is_some_and(|age| age > max_age)
+        })
+    }
+
+    /// Returns how many values per second are added with [`AggregateMetric::add_at`].
+    ///
+    /// The rate is measured over one second intervals. If no interval has closed within
+    /// the last second, e.g. because the source has stalled, the rate is estimated from the
+    /// values added since the last closed interval, so that it falls toward zero.
+    /// Returns `0.0` if no value has been added with [`AggregateMetric::add_at`].
+    pub fn sample_rate(&self, now: Millis) -> f32 {
+        match &self.sample_rate {
+            Some(rate) if rate.is_stale(now, rate.interval()) => rate.estimated_rate(now),
+            Some(rate) => rate.rate(),
+            None => 0.0,
+        }
+    }
+
+    /// Checks that the value is finite and can be used for the configured mean, and counts
+    /// the value as [rejected](AggregateMetric::rejected_count) if it isn't finite.
+    fn check_value(&mut self, value: T) -> Result<(), MetricError> {
+        let value_f64 = value.to_f64().unwrap_or(f64::NAN);
+        if !value_f64.is_finite() {
+            self.rejected_count += 1;
+            return Err(MetricError::NonFinite);
+        }
+        if self.mean == Mean::Geometric && value_f64 <= 0.0 {
+            return Err(MetricError::NonPositive);
+        }
+        if self.mean == Mean::Harmonic && value_f64 == 0.0 {
+            return Err(MetricError::Zero);
+        }
+        Ok(())
+    }
+
+    fn sum_overflows(&self, value: T) -> bool {
+        let zero = T::default();
+        if value > zero {
+            self.sum > T::max_value() - value
+        } else if value < zero {
+            self.sum < T::min_value() - value
+        } else {
+            false
+        }
+    }
+
+    fn saturating_sum(&self, value: T) -> T {
+        if !self.sum_overflows(value) {
+            self.sum + value
+        } else if value > T::default() {
+            T::max_value()
+        } else {
+            T::min_value()
+        }
+    }
+
+    /// Adds the value to the accumulators, except the sum, and returns `true` if the
+    /// threshold is reached.
+    fn add_to_window(&mut self, value: T, weight: f64) -> bool {
+        let value_f64 = value.to_f64().unwrap_or(0.0);
+        self.sum_f64 += value_f64;
+        self.sum_of_squares += value_f64 * value_f64;
+        self.weighted_sum += value_f64 * weight;
+        self.weight_sum += weight;
+        self.mean_sum += match self.mean {
+            Mean::Arithmetic => 0.0,
+            Mean::Geometric => value_f64.ln(),
+            Mean::Harmonic => value_f64.recip(),
+            Mean::Trimmed(_) => 0.0,
+        };
+        if let Some(samples) = &mut self.samples {
+            samples.push(value);
+        }
+        self.last_value = Some(value);
+
+        // Update the max and min acknowledgments. The first value of a window replaces the
+        // sentinels instead of being compared against them.
+        if self.count == 0 || value > self.max_ack {
+            self.max_ack = value;
+        }
+        if self.count == 0 || value < self.min_ack {
+            self.min_ack = value;
+        }
+        self.count += 1;
+
+        self.count >= self.threshold
+    }
+
+    /// Folds the window in progress of `other` into the window in progress of `self`.
+    ///
+    /// If the combined count reaches the threshold, the combined window is published as
+    /// a whole, so it can contain more values than the threshold. The published values of
+    /// `other` are not used. Both metrics are expected to have the same unit.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MetricError::Incompatible`] if only one of the metrics buffers its values,
+    /// e.g. with [`AggregateMetric::new_buffered`], or if they calculate different kinds of
+    /// mean, including trimmed means with different trim fractions. `self` is left
+    /// unchanged.
+    pub fn merge(&mut self, other: &AggregateMetric<T>) -> Result<(), MetricError> {
+        debug_assert_eq!(
+            self.unit, other.unit,
+            "merged metrics must have the same unit"
+        );
+        if self.samples.is_some() != other.samples.is_some() || self.mean != other.mean {
+            return Err(MetricError::Incompatible);
+        }
+        if other.count == 0 {
+            return Ok(());
+        }
+
+        self.sum = self.saturating_sum(other.sum);
+        self.sum_f64 += other.sum_f64;
+        self.sum_of_squares += other.sum_of_squares;
+        self.mean_sum += other.mean_sum;
+        self.weighted_sum += other.weighted_sum;
+        self.weight_sum += other.weight_sum;
+        self.is_weighted |= other.is_weighted;
+        if self.count == 0 || other.max_ack > self.max_ack {
+            self.max_ack = other.max_ack;
+        }
+        if self.count == 0 || other.min_ack < self.min_ack {
+            self.min_ack = other.min_ack;
+        }
+        self.count += other.count;
+        if let (Some(samples), Some(other_samples)) = (&mut self.samples, &other.samples) {
+            samples.extend_from_slice(other_samples);
+        }
+
+        if self.count >= self.threshold {
+            self.publish();
+        }
+        Ok(())
+    }
+
+    /// Calculates the stats of the window in progress and starts a new window.
+    fn publish(&mut self) {
+        self.publish_with(|_| {});
+    }
+
+    /// Calculates the stats of the window in progress, calls `f` with them and starts a
+    /// new window.
+    fn publish_with<F: FnMut(&MinMaxAvg<T>)>(&mut self, mut f: F) {
+        self.avg_f64 = self.window_mean();
+        self.avg = self.avg_f64 as f32;
+
+        // Single pass population variance, E[x^2] - E[x]^2
+        let mean = self.sum_f64 / f64::from(self.count);
+        let mean_of_squares = self.sum_of_squares / f64::from(self.count);
+        self.variance = (mean_of_squares - mean * mean).max(0.0) as f32;
+
+        self.min = self.min_ack;
+        self.max = self.max_ack;
+        self.published_count = self.count;
+        self.published_sum = self.sum;
+        self.avg_is_set = true;
+
+        if let Some(samples) = &mut self.samples {
+            samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            core::mem::swap(&mut self.published_samples, samples);
+        }
+
+        let values = MinMaxAvg::new(self.min, self.avg, self.max).with_unit(self.unit);
+        f(&values);
+        if self.history_capacity > 0 {
+            if self.history.len() == self.history_capacity {
+                self.history.pop_front();
+            }
+            self.history.push_back(values);
+        }
+
+        self.reset_window();
+    }
+
+    /// Discards the values of the window in progress.
+    fn reset_window(&mut self) {
+        self.max_ack = T::min_value();
+        self.min_ack = T::max_value();
+        self.count = 0;
+        self.sum = T::default();
+        self.sum_f64 = 0.0;
+        self.sum_of_squares = 0.0;
+        self.mean_sum = 0.0;
+        self.weighted_sum = 0.0;
+        self.weight_sum = 0.0;
+        self.is_weighted = false;
+        if let Some(samples) = &mut self.samples {
+            samples.clear();
+        }
+    }
+
+    /// Discards the values of the window in progress, but keeps the last published values.
+    ///
+    /// The most recently added value is discarded along with the window, so
+    /// [`AggregateMetric::normalized_last`] returns `None` until a value is added. Use
+    /// [`AggregateMetric::clear`] to also discard the published values.
+    pub fn reset(&mut self) {
+        self.reset_window();
+        self.last_value = None;
+    }
+
+    /// Discards the window in progress, but keeps the last published values.
+    ///
+    /// Same as [`AggregateMetric::reset`].
+    pub fn reset_keep_last(&mut self) {
+        self.reset();
+    }
+
+    /// Discards the window in progress, the last published values, the
+    /// [history](AggregateMetric::history) and the tracked
+    /// [sample rate](AggregateMetric::sample_rate), so that [`AggregateMetric::values`]
+    /// returns `None` until a new window completes.
+    ///
+    /// The configuration, e.g. the threshold, the unit and the kind of mean, is kept.
+    pub fn clear(&mut self) {
+        self.reset_window();
+        self.min = T::default();
+        self.max = T::default();
+        self.avg = 0.0;
+        self.avg_f64 = 0.0;
+        self.variance = 0.0;
+        self.published_count = 0;
+        self.published_sum = T::default();
+        self.avg_is_set = false;
+        self.published_samples.clear();
+        self.history.clear();
+        self.last_sample_at = None;
+        self.sample_rate = None;
+        self.last_value = None;
+        self.rejected_count = 0;
+    }
+
+    /// Returns the values of the retained completed windows, from the oldest to the most
+    /// recent.
+    ///
+    /// Empty unless the metric was created with [`AggregateMetric::new_with_history`]. When
+    /// the capacity is exceeded, the oldest window is evicted.
+    pub fn history(&self) -> impl Iterator<Item = &MinMaxAvg<T>> {
+        self.history.iter()
+    }
+
+    /// Returns the minimum, average, and maximum values as a tuple, if available.
     pub fn values(&self) -> Option<MinMaxAvg<T>> {
         if self.avg_is_set {
             Some(MinMaxAvg::new(self.min, self.avg, self.max).with_unit(self.unit))
@@ -226,4 +1940,189 @@ where
             None
         }
     }
+
+    /// Returns all the stats of the last completed window at once, if available.
+    pub fn snapshot(&self) -> Option<AggregateSnapshot<T>> {
+        self.avg_is_set.then_some(AggregateSnapshot {
+            min: self.min,
+            avg: self.avg,
+            max: self.max,
+            count: self.published_count,
+            sum: self.published_sum,
+            unit: self.unit,
+        })
+    }
+
+    /// Returns the minimum, average, and maximum values of the window in progress,
+    /// returning `None` if no values have been added to it.
+    ///
+    /// Unlike [`AggregateMetric::values`], this changes with every added value.
+    pub fn partial_values(&self) -> Option<MinMaxAvg<T>> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(
+            MinMaxAvg::new(self.min_ack, self.window_mean() as f32, self.max_ack)
+                .with_unit(self.unit),
+        )
+    }
+
+    /// Calculates the configured mean of the window in progress.
+    fn window_mean(&self) -> f64 {
+        match self.mean {
+            Mean::Arithmetic if self.is_weighted && self.weight_sum > 0.0 => {
+                self.weighted_sum / self.weight_sum
+            }
+            // Summed as f64, so that large integer sums neither saturate nor lose precision
+            // before the division
+            Mean::Arithmetic => self.sum_f64 / f64::from(self.count),
+            Mean::Geometric => (self.mean_sum / f64::from(self.count)).exp(),
+            Mean::Harmonic => f64::from(self.count) / self.mean_sum,
+            Mean::Trimmed(trim_fraction) => {
+                let mut sorted = self.samples.clone().unwrap_or_default();
+                sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                let trimmed = (sorted.len() as f32 * trim_fraction) as usize;
+                let kept = &sorted[trimmed..sorted.len() - trimmed];
+                let sum: f64 = kept.iter().map(|value| value.to_f64().unwrap_or(0.0)).sum();
+                sum / kept.len() as f64
+            }
+        }
+    }
+}
+
+/// Displays the last published values, or `no data` before the first window completes.
+impl<T> Display for AggregateMetric<T>
+where
+    T: Add<Output = T>
+        + Sub<Output = T>
+        + Div<Output = T>
+        + Copy
+        + PartialOrd
+        + Default
+        + From<u8>
+        + Debug
+        + Display
+        + Bounded
+        + ToPrimitive,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.values() {
+            Some(values) => write!(f, "{}", values),
+            None => write!(f, "no data"),
+        }
+    }
+}
+
+/// Creates an `AggregateMetric` with a threshold of one, which publishes every value as its
+/// own window.
+impl<T> Default for AggregateMetric<T>
+where
+    T: Add<Output = T>
+        + Sub<Output = T>
+        + Div<Output = T>
+        + Copy
+        + PartialOrd
+        + Default
+        + From<u8>
+        + Debug
+        + Display
+        + Bounded
+        + ToPrimitive,
+{
+    fn default() -> Self {
+        Self::new(1).expect("1 is not zero")
+    }
+}
+
+impl<T> Extend<T> for AggregateMetric<T>
+where
+    T: Add<Output = T>
+        + Sub<Output = T>
+        + Div<Output = T>
+        + Copy
+        + PartialOrd
+        + Default
+        + From<u8>
+        + Debug
+        + Display
+        + Bounded
+        + ToPrimitive,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.add(value);
+        }
+    }
+}
+
+/// Collects all values into a single window, using the number of finite values as the
+/// threshold. Non-finite values are skipped and counted by
+/// [`AggregateMetric::rejected_count`], like with [`AggregateMetric::add`].
+impl<T> FromIterator<T> for AggregateMetric<T>
+where
+    T: Add<Output = T>
+        + Sub<Output = T>
+        + Div<Output = T>
+        + Copy
+        + PartialOrd
+        + Default
+        + From<u8>
+        + Debug
+        + Display
+        + Bounded
+        + ToPrimitive,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let (values, rejected): (Vec<T>, Vec<T>) = iter
+            .into_iter()
+            .partition(|value| value.to_f64().is_some_and(f64::is_finite));
+        let threshold = u32::try_from(values.len()).unwrap_or(u32::MAX).max(1);
+        let mut metric = Self::new(threshold).expect("threshold is at least one");
+        metric.add_slice(&values);
+        metric.rejected_count = rejected.len() as u64;
+        metric
+    }
+}
+
+/// Combines the last completed windows of several metrics into one summary, e.g. the
+/// latency of a whole fleet from per-region metrics.
+///
+/// The minimum and maximum are taken over all windows, and the average is the average of
+/// the window averages weighted by their number of values. The unit of the first metric
+/// with data is used. Metrics without a completed window are skipped, and `None` is
+/// returned if none of them has one.
+pub fn combine<T>(metrics: &[&AggregateMetric<T>]) -> Option<MinMaxAvg<T>>
+where
+    T: Add<Output = T>
+        + Sub<Output = T>
+        + Div<Output = T>
+        + Copy
+        + PartialOrd
+        + Default
+        + From<u8>
+        + Debug
+        + Display
+        + Bounded
+        + ToPrimitive,
+{
+    let mut snapshots = metrics.iter().filter_map(|metric| metric.snapshot());
+    let first = snapshots.next()?;
+
+    let mut min = first.min;
+    let mut max = first.max;
+    let mut weighted_sum = f64::from(first.avg) * f64::from(first.count);
+    let mut count = u64::from(first.count);
+    for snapshot in snapshots {
+        if snapshot.min < min {
+            min = snapshot.min;
+        }
+        if snapshot.max > max {
+            max = snapshot.max;
+        }
+        weighted_sum += f64::from(snapshot.avg) * f64::from(snapshot.count);
+        count += u64::from(snapshot.count);
+    }
+
+    let avg = (weighted_sum / count as f64) as f32;
+    Some(MinMaxAvg::new(min, avg, max).with_unit(first.unit))
 }
@@ -0,0 +1,77 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::{MetricError, RateMetric};
+use monotonic_time_rs::{Millis, MillisDuration};
+
+/// Evaluates the rate of a single event stream over several measurement intervals at once.
+#[derive(Debug)]
+pub struct MultiRateMetric {
+    windows: Vec<RateMetric>,
+}
+
+impl MultiRateMetric {
+    /// Creates a new `MultiRateMetric` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The initial [`Millis`] from which time tracking starts.
+    /// * `intervals` - The measurement interval of each window, e.g. 1, 10 and 60 seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MetricError::InvalidInterval`] if any of the intervals is zero.
+    pub fn new(time: Millis, intervals: &[MillisDuration]) -> Result<Self, MetricError> {
+        let windows = intervals
+            .iter()
+            .map(|&interval| {
+                let mut window = RateMetric::new(time);
+                window.set_interval(interval)?;
+                Ok(window)
+            })
+            .collect::<Result<_, MetricError>>()?;
+
+        Ok(Self { windows })
+    }
+
+    /// Increments the event count of every window by one.
+    pub fn increment(&mut self) {
+        for window in &mut self.windows {
+            window.increment();
+        }
+    }
+
+    /// Adds a specified number of events to every window.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of events to add.
+    pub fn add(&mut self, count: u32) {
+        for window in &mut self.windows {
+            window.add(count);
+        }
+    }
+
+    /// Updates every window whose measurement interval has elapsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The current [`Millis`] representing the time at which the update is triggered.
+    pub fn update(&mut self, time: Millis) {
+        for window in &mut self.windows {
+            window.update(time);
+        }
+    }
+
+    /// Returns the rate of the window at `interval_index`, in the order given to
+    /// [`MultiRateMetric::new`].
+    pub fn rate(&self, interval_index: usize) -> Option<f32> {
+        self.windows.get(interval_index).map(RateMetric::rate)
+    }
+
+    /// Returns the rates of all windows, in the order given to [`MultiRateMetric::new`].
+    pub fn rates(&self) -> Vec<f32> {
+        self.windows.iter().map(RateMetric::rate).collect()
+    }
+}
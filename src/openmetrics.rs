@@ -0,0 +1,98 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! Renders metrics in the OpenMetrics text format.
+//!
+//! Rates and aggregates are exported as gauges, the same way as by [`crate::prometheus`],
+//! whose functions can be used to append them. Histograms are exported with cumulative
+//! buckets and can carry an [`Exemplar`]. The exposition must be terminated with
+//! [`write_eof`].
+
+use crate::prometheus::{self, escape_help, escape_label_value, format_labels, Label};
+use crate::{AggregateMetric, Histogram, MetricRegistry};
+use core::fmt::Write;
+
+/// A trace id attached to the histogram bucket that a sampled value fell into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exemplar<'a> {
+    pub trace_id: &'a str,
+    pub value: f32,
+}
+
+impl<'a> Exemplar<'a> {
+    /// Creates an exemplar for the maximum value of the last completed window of
+    /// `aggregate`, or `None` if no window has completed yet.
+    pub fn at_max(aggregate: &AggregateMetric<f32>, trace_id: &'a str) -> Option<Self> {
+        aggregate.values().map(|values| Self {
+            trace_id,
+            value: values.max,
+        })
+    }
+}
+
+/// Appends a [`Histogram`] as `name_bucket` samples with cumulative counts, followed by
+/// `name_count`.
+///
+/// The `exemplar`, if any, is attached to the bucket that its value falls into.
+pub fn write_histogram(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    labels: &[Label],
+    histogram: &Histogram<f32>,
+    exemplar: Option<Exemplar>,
+) {
+    let _ = writeln!(out, "# HELP {name} {}", escape_help(help));
+    let _ = writeln!(out, "# TYPE {name} histogram");
+
+    let exemplar_bucket = exemplar.map(|exemplar| {
+        histogram
+            .boundaries()
+            .iter()
+            .position(|&boundary| exemplar.value <= boundary)
+            .unwrap_or(histogram.boundaries().len())
+    });
+
+    let mut cumulative = 0;
+    for (index, count) in histogram.buckets().iter().enumerate() {
+        cumulative += count;
+        let le = histogram
+            .boundaries()
+            .get(index)
+            .map_or_else(|| "+Inf".to_string(), |boundary| format!("{boundary:?}"));
+        let mut bucket_labels = labels.to_vec();
+        bucket_labels.push(("le", &le));
+
+        let _ = write!(
+            out,
+            "{name}_bucket{} {cumulative}",
+            format_labels(&bucket_labels)
+        );
+        if let Some(exemplar) = exemplar.filter(|_| exemplar_bucket == Some(index)) {
+            let _ = write!(
+                out,
+                " # {{trace_id=\"{}\"}} {:?}",
+                escape_label_value(exemplar.trace_id),
+                exemplar.value
+            );
+        }
+        out.push('\n');
+    }
+
+    let _ = writeln!(out, "{name}_count{} {cumulative}", format_labels(labels));
+}
+
+/// Appends the `# EOF` line that terminates an OpenMetrics exposition.
+pub fn write_eof(out: &mut String) {
+    out.push_str("# EOF\n");
+}
+
+/// Renders all metrics of the registry, like [`prometheus::render_registry`], terminated
+/// with `# EOF`.
+pub fn render_registry(registry: &MetricRegistry) -> String {
+    let mut out = prometheus::render_registry(registry);
+    write_eof(&mut out);
+    out
+}
@@ -0,0 +1,147 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::MetricError;
+
+/// Estimates a single quantile of a stream with constant memory, using the P² algorithm by
+/// Jain and Chlamtac.
+///
+/// Five markers track the minimum, the maximum, the estimated quantile and two quantiles
+/// halfway to the extremes. The marker heights are adjusted with a piecewise parabolic
+/// prediction as values are observed, so no values need to be stored.
+#[derive(Debug, Clone)]
+pub struct P2Quantile {
+    p: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired_positions: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    /// Creates a new `P2Quantile` estimating the quantile `p`, e.g. `0.99` for p99.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `p` is not strictly between `0.0` and `1.0`.
+    pub fn new(p: f32) -> Result<Self, MetricError> {
+        if !(p > 0.0 && p < 1.0) {
+            return Err(MetricError::InvalidQuantile);
+        }
+        let p = f64::from(p);
+        Ok(Self {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [0.0, 1.0, 2.0, 3.0, 4.0],
+            desired_positions: [0.0, 2.0 * p, 4.0 * p, 2.0 + 2.0 * p, 4.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        })
+    }
+
+    /// Observes a value. Non-finite values are ignored.
+    pub fn observe(&mut self, value: f32) {
+        if !value.is_finite() {
+            return;
+        }
+        let value = f64::from(value);
+
+        if self.count < 5 {
+            self.heights[self.count] = value;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        // Find the cell that the value falls into, extending the extremes if needed
+        let cell = if value < self.heights[0] {
+            self.heights[0] = value;
+            0
+        } else if value >= self.heights[4] {
+            self.heights[4] = value;
+            3
+        } else {
+            (1..5)
+                .position(|index| value < self.heights[index])
+                .expect("value is below the maximum marker")
+        };
+
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired_positions.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+
+        for index in 1..4 {
+            self.adjust_marker(index);
+        }
+    }
+
+    fn adjust_marker(&mut self, index: usize) {
+        let offset = self.desired_positions[index] - self.positions[index];
+        let can_move_up = self.positions[index + 1] - self.positions[index] > 1.0;
+        let can_move_down = self.positions[index - 1] - self.positions[index] < -1.0;
+        if !((offset >= 1.0 && can_move_up) || (offset <= -1.0 && can_move_down)) {
+            return;
+        }
+
+        let direction = offset.signum();
+        let parabolic = self.parabolic(index, direction);
+        self.heights[index] =
+            if self.heights[index - 1] < parabolic && parabolic < self.heights[index + 1] {
+                parabolic
+            } else {
+                self.linear(index, direction)
+            };
+        self.positions[index] += direction;
+    }
+
+    fn parabolic(&self, index: usize, direction: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        q[index]
+            + direction / (n[index + 1] - n[index - 1])
+                * ((n[index] - n[index - 1] + direction) * (q[index + 1] - q[index])
+                    / (n[index + 1] - n[index])
+                    + (n[index + 1] - n[index] - direction) * (q[index] - q[index - 1])
+                        / (n[index] - n[index - 1]))
+    }
+
+    fn linear(&self, index: usize, direction: f64) -> f64 {
+        let neighbour = if direction > 0.0 {
+            index + 1
+        } else {
+            index - 1
+        };
+        self.heights[index]
+            + direction * (self.heights[neighbour] - self.heights[index])
+                / (self.positions[neighbour] - self.positions[index])
+    }
+
+    /// Returns the estimated quantile, or `None` if no values have been observed.
+    ///
+    /// Until five values have been observed, the quantile is taken from the observed
+    /// values directly.
+    pub fn quantile(&self) -> Option<f32> {
+        match self.count {
+            0 => None,
+            1..=4 => {
+                let mut observed = self.heights[..self.count].to_vec();
+                observed.sort_by(f64::total_cmp);
+                let rank = (self.p * self.count as f64).ceil() as usize;
+                Some(observed[rank.clamp(1, self.count) - 1] as f32)
+            }
+            _ => Some(self.heights[2] as f32),
+        }
+    }
+
+    /// Returns the number of observed values.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
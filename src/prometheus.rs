@@ -0,0 +1,107 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! Renders metrics in the Prometheus text exposition format.
+//!
+//! Rates are exported as gauges. Aggregates are exported as three gauges, suffixed with
+//! `_min`, `_avg` and `_max`, and are left out until their first window completes.
+
+use crate::{AggregateMetric, MetricRegistry, RateMetric};
+use core::fmt::{Debug, Display, Write};
+use core::ops::{Add, Div, Sub};
+use num_traits::{Bounded, ToPrimitive};
+
+/// A label name and value attached to every sample line of a metric.
+pub type Label<'a> = (&'a str, &'a str);
+
+/// Appends a [`RateMetric`] as a gauge named `name`.
+pub fn write_rate(out: &mut String, name: &str, help: &str, labels: &[Label], rate: &RateMetric) {
+    write_gauge(out, name, help, labels, rate.rate());
+}
+
+/// Appends an [`AggregateMetric`] as the gauges `name_min`, `name_avg` and `name_max`.
+///
+/// Nothing is appended if the aggregate hasn't completed a window yet.
+pub fn write_aggregate<T>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    labels: &[Label],
+    aggregate: &AggregateMetric<T>,
+) where
+    T: Add<Output = T>
+        + Sub<Output = T>
+        + Div<Output = T>
+        + Copy
+        + PartialOrd
+        + Default
+        + From<u8>
+        + Debug
+        + Display
+        + Bounded
+        + ToPrimitive,
+{
+    let Some(values) = aggregate.values() else {
+        return;
+    };
+    write_gauge(
+        out,
+        &format!("{name}_min"),
+        &format!("{help} (minimum)"),
+        labels,
+        values.min,
+    );
+    write_gauge(
+        out,
+        &format!("{name}_avg"),
+        &format!("{help} (average)"),
+        labels,
+        values.avg,
+    );
+    write_gauge(
+        out,
+        &format!("{name}_max"),
+        &format!("{help} (maximum)"),
+        labels,
+        values.max,
+    );
+}
+
+/// Renders all metrics of the registry, in the order of [`MetricRegistry::iter`].
+pub fn render_registry(registry: &MetricRegistry) -> String {
+    let mut out = String::new();
+    for (name, rate) in registry.rate_metrics() {
+        write_rate(&mut out, name, &format!("{name} per second"), &[], rate);
+    }
+    for (name, aggregate) in registry.aggregate_metrics() {
+        write_aggregate(&mut out, name, name, &[], aggregate);
+    }
+    out
+}
+
+fn write_gauge(out: &mut String, name: &str, help: &str, labels: &[Label], value: impl Display) {
+    let _ = writeln!(out, "# HELP {name} {}", escape_help(help));
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    let _ = writeln!(out, "{name}{} {value}", format_labels(labels));
+}
+
+pub(crate) fn format_labels(labels: &[Label]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| format!("{name}=\"{}\"", escape_label_value(value)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+pub(crate) fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+pub(crate) fn escape_label_value(value: &str) -> String {
+    escape_help(value).replace('"', "\\\"")
+}
@@ -0,0 +1,86 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::{EwmaMetric, MetricError};
+
+/// Tracks the ratio of hits to all recorded outcomes, e.g. a cache hit ratio.
+#[derive(Debug, Clone, Copy)]
+pub struct RatioMetric {
+    hits: u64,
+    misses: u64,
+    smoothed: Option<EwmaMetric>,
+}
+
+impl RatioMetric {
+    /// Creates a new `RatioMetric` without any recorded outcomes.
+    pub const fn new() -> Self {
+        Self {
+            hits: 0,
+            misses: 0,
+            smoothed: None,
+        }
+    }
+
+    /// Creates a new `RatioMetric` that also tracks an exponentially weighted moving
+    /// average of the outcomes, see [`RatioMetric::smoothed_ratio`].
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - The weight of each new outcome, in the range `0.0..=1.0`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `alpha` is outside of `0.0..=1.0`.
+    pub fn with_smoothing(alpha: f32) -> Result<Self, MetricError> {
+        Ok(Self {
+            smoothed: Some(EwmaMetric::new(alpha)?),
+            ..Self::new()
+        })
+    }
+
+    /// Records a hit.
+    pub fn record_hit(&mut self) {
+        self.hits = self.hits.saturating_add(1);
+        if let Some(smoothed) = &mut self.smoothed {
+            smoothed.update(1.0);
+        }
+    }
+
+    /// Records a miss.
+    pub fn record_miss(&mut self) {
+        self.misses = self.misses.saturating_add(1);
+        if let Some(smoothed) = &mut self.smoothed {
+            smoothed.update(0.0);
+        }
+    }
+
+    /// Returns the number of recorded hits.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of recorded misses.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Returns `hits / (hits + misses)`, or `None` if nothing has been recorded.
+    pub fn ratio(&self) -> Option<f32> {
+        // Summed as f64, since the saturating counts can add up to more than u64::MAX
+        let total = self.hits as f64 + self.misses as f64;
+        (total > 0.0).then(|| (self.hits as f64 / total) as f32)
+    }
+
+    /// Returns the smoothed ratio, or `None` if nothing has been recorded or if the metric
+    /// wasn't created with [`RatioMetric::with_smoothing`].
+    pub fn smoothed_ratio(&self) -> Option<f32> {
+        self.smoothed?.value()
+    }
+}
+
+impl Default for RatioMetric {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,97 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::{AggregateMetric, MetricError, RateMetric};
+use core::fmt::Display;
+use monotonic_time_rs::Millis;
+use std::collections::BTreeMap;
+
+/// Holds named [`RateMetric`] and [`AggregateMetric`] instances, created on first use.
+#[derive(Debug)]
+pub struct MetricRegistry {
+    rates: BTreeMap<&'static str, RateMetric>,
+    aggregates: BTreeMap<&'static str, AggregateMetric<f32>>,
+    aggregate_threshold: u32,
+    now: Millis,
+}
+
+impl MetricRegistry {
+    /// Creates a new, empty `MetricRegistry`.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The time from which rate metrics created by [`MetricRegistry::rate`] start.
+    /// * `aggregate_threshold` - The threshold of aggregate metrics created by
+    ///   [`MetricRegistry::aggregate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `aggregate_threshold` is zero.
+    pub fn new(now: Millis, aggregate_threshold: u32) -> Result<Self, MetricError> {
+        if aggregate_threshold == 0 {
+            return Err(MetricError::ZeroThreshold);
+        }
+        Ok(Self {
+            rates: BTreeMap::new(),
+            aggregates: BTreeMap::new(),
+            aggregate_threshold,
+            now,
+        })
+    }
+
+    /// Returns the rate metric with the given name, creating it if it doesn't exist.
+    ///
+    /// A new metric starts at the time of the latest [`MetricRegistry::update_all`].
+    pub fn rate(&mut self, name: &'static str) -> &mut RateMetric {
+        let now = self.now;
+        self.rates
+            .entry(name)
+            .or_insert_with(|| RateMetric::new(now))
+    }
+
+    /// Returns the aggregate metric with the given name, creating it if it doesn't exist.
+    pub fn aggregate(&mut self, name: &'static str) -> &mut AggregateMetric<f32> {
+        let threshold = self.aggregate_threshold;
+        self.aggregates.entry(name).or_insert_with(|| {
+            AggregateMetric::new(threshold).expect("threshold was checked in MetricRegistry::new")
+        })
+    }
+
+    /// Updates all rate metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current [`Millis`] passed to [`RateMetric::update`].
+    pub fn update_all(&mut self, now: Millis) {
+        self.now = now;
+        for rate in self.rates.values_mut() {
+            rate.update(now);
+        }
+    }
+
+    /// Iterates over the rate metrics, sorted by name.
+    pub fn rate_metrics(&self) -> impl Iterator<Item = (&'static str, &RateMetric)> {
+        self.rates.iter().map(|(&name, rate)| (name, rate))
+    }
+
+    /// Iterates over the aggregate metrics, sorted by name.
+    pub fn aggregate_metrics(&self) -> impl Iterator<Item = (&'static str, &AggregateMetric<f32>)> {
+        self.aggregates
+            .iter()
+            .map(|(&name, aggregate)| (name, aggregate))
+    }
+
+    /// Iterates over all metrics by name, rate metrics first, each group sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &dyn Display)> {
+        let rates = self
+            .rates
+            .iter()
+            .map(|(&name, rate)| (name, rate as &dyn Display));
+        let aggregates = self
+            .aggregates
+            .iter()
+            .map(|(&name, aggregate)| (name, aggregate as &dyn Display));
+        rates.chain(aggregates)
+    }
+}
@@ -0,0 +1,110 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::MetricError;
+use core::cmp::Ordering;
+use num_traits::ToPrimitive;
+
+/// Keeps a fixed size uniform random sample of an unbounded stream, using Vitter's
+/// algorithm R, so that quantiles can be queried with predictable memory.
+///
+/// The random numbers come from a small built-in SplitMix64 generator, so no random number
+/// dependency is needed. Use [`Reservoir::with_seed`] for reproducible samples.
+#[derive(Debug, Clone)]
+pub struct Reservoir<T> {
+    samples: Vec<T>,
+    capacity: usize,
+    seen: u64,
+    rng_state: u64,
+}
+
+impl<T: Copy + PartialOrd + ToPrimitive> Reservoir<T> {
+    /// Creates a new `Reservoir` that keeps up to `capacity` samples, with a fixed seed.
+    pub fn new(capacity: usize) -> Result<Self, MetricError> {
+        Self::with_seed(capacity, 0x853C_49E6_748F_EA9B)
+    }
+
+    /// Creates a new `Reservoir` that keeps up to `capacity` samples, seeding the random
+    /// number generator with `seed`.
+    pub fn with_seed(capacity: usize, seed: u64) -> Result<Self, MetricError> {
+        if capacity == 0 {
+            return Err(MetricError::ZeroCapacity);
+        }
+        Ok(Self {
+            samples: Vec::with_capacity(capacity),
+            capacity,
+            seen: 0,
+            rng_state: seed,
+        })
+    }
+
+    /// Offers a value to the reservoir. Once the reservoir is full, the value replaces a
+    /// random sample with a probability of `capacity / seen`.
+    pub fn add(&mut self, value: T) {
+        self.seen += 1;
+        if self.samples.len() < self.capacity {
+            self.samples.push(value);
+            return;
+        }
+
+        let index = self.next_random() % self.seen;
+        if let Some(sample) = self.samples.get_mut(index as usize) {
+            *sample = value;
+        }
+    }
+
+    /// Returns the number of values offered to the reservoir.
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// Returns the samples currently kept, in no particular order.
+    pub fn samples(&self) -> &[T] {
+        &self.samples
+    }
+
+    /// Returns the `q`th percentile of the samples, using the nearest-rank method.
+    ///
+    /// Returns `None` if `q` is outside of `0.0..=100.0` or if the reservoir is empty.
+    pub fn percentile(&self, q: f32) -> Option<T> {
+        if !(0.0..=100.0).contains(&q) || self.samples.is_empty() {
+            return None;
+        }
+        let sorted = self.sorted();
+        let rank = ((q / 100.0) * sorted.len() as f32).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+
+    /// Returns the median of the samples, interpolating the two middle values for an even
+    /// number of samples, or `None` if the reservoir is empty.
+    pub fn median(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let sorted = self.sorted();
+        let len = sorted.len();
+        let upper = sorted[len / 2].to_f32()?;
+        if len % 2 == 1 {
+            Some(upper)
+        } else {
+            let lower = sorted[len / 2 - 1].to_f32()?;
+            Some((lower + upper) / 2.0)
+        }
+    }
+
+    fn sorted(&self) -> Vec<T> {
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        sorted
+    }
+
+    /// SplitMix64
+    fn next_random(&mut self) -> u64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
@@ -0,0 +1,57 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! Serializes the `monotonic_time_rs` types as plain milliseconds, since they don't
+//! implement `serde` themselves.
+
+pub mod millis {
+    use monotonic_time_rs::Millis;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &Millis, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(time.absolute_milliseconds())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Millis, D::Error> {
+        u64::deserialize(deserializer).map(Millis::new)
+    }
+}
+
+pub mod millis_duration {
+    use monotonic_time_rs::MillisDuration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        duration: &MillisDuration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<MillisDuration, D::Error> {
+        u64::deserialize(deserializer).map(MillisDuration::from_millis)
+    }
+}
+
+pub mod option_millis {
+    use monotonic_time_rs::Millis;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<Millis>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        time.map(|time| time.absolute_milliseconds())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Millis>, D::Error> {
+        Option::<u64>::deserialize(deserializer).map(|time| time.map(Millis::new))
+    }
+}
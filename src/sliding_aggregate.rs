@@ -0,0 +1,95 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::{MetricError, MinMaxAvg};
+use core::fmt::Display;
+use num_traits::ToPrimitive;
+use std::collections::VecDeque;
+
+/// Tracks minimum, maximum, and average values over the most recent samples.
+///
+/// Unlike [`crate::AggregateMetric`], which starts over with an empty window when the
+/// threshold is reached, the oldest sample is evicted for every new sample once the
+/// buffer is full.
+#[derive(Debug)]
+pub struct SlidingAggregate<T> {
+    samples: VecDeque<T>,
+    capacity: usize,
+    min: T,
+    avg: f32,
+    max: T,
+    unit: &'static str,
+}
+
+impl<T> SlidingAggregate<T>
+where
+    T: Copy + PartialOrd + Default + Display + ToPrimitive,
+{
+    /// Creates a new `SlidingAggregate` that retains the last `capacity` samples.
+    pub fn new(capacity: usize) -> Result<Self, MetricError> {
+        if capacity == 0 {
+            Err(MetricError::ZeroCapacity)
+        } else {
+            Ok(Self {
+                samples: VecDeque::with_capacity(capacity),
+                capacity,
+                min: T::default(),
+                avg: 0.0,
+                max: T::default(),
+                unit: "",
+            })
+        }
+    }
+
+    pub fn with_unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Adds a value, evicting the oldest one if the buffer is full, and recalculates the
+    /// stats over the whole buffer.
+    pub fn add(&mut self, value: T) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+
+        let mut min = value;
+        let mut max = value;
+        let mut sum = 0.0;
+        for &sample in &self.samples {
+            if sample < min {
+                min = sample;
+            }
+            if sample > max {
+                max = sample;
+            }
+            sum += sample.to_f64().unwrap_or(0.0);
+        }
+
+        self.min = min;
+        self.max = max;
+        self.avg = (sum / self.samples.len() as f64) as f32;
+    }
+
+    /// Returns the number of samples currently in the buffer.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns `true` if no samples have been added.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns the minimum, average, and maximum values over the buffer, if it has at
+    /// least one sample.
+    pub fn values(&self) -> Option<MinMaxAvg<T>> {
+        if self.samples.is_empty() {
+            None
+        } else {
+            Some(MinMaxAvg::new(self.min, self.avg, self.max).with_unit(self.unit))
+        }
+    }
+}
@@ -0,0 +1,25 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+
+//! Formats metric values as StatsD lines.
+//!
+//! The functions only produce the lines; sending them, e.g. over UDP, is left to the caller.
+
+use monotonic_time_rs::MillisDuration;
+
+/// Formats a gauge line, `name:value|g`.
+pub fn gauge(name: &str, value: f32) -> String {
+    format!("{name}:{value}|g")
+}
+
+/// Formats a counter line, `name:count|c`.
+pub fn counter(name: &str, count: u64) -> String {
+    format!("{name}:{count}|c")
+}
+
+/// Formats a timing line in milliseconds, `name:millis|ms`.
+pub fn timing(name: &str, duration: MillisDuration) -> String {
+    format!("{name}:{}|ms", duration.as_millis())
+}
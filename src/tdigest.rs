@@ -0,0 +1,193 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::MetricError;
+use core::cmp::Ordering;
+use core::f64::consts::PI;
+
+/// A cluster of nearby values, represented by their mean and their number.
+#[derive(Debug, Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// Estimates any quantile of an unbounded stream with bounded memory, using the merging
+/// t-digest by Dunning and Ertl.
+///
+/// Values are clustered into centroids that are small near the extremes and larger near
+/// the median, so that high quantiles such as p99 and p999 stay accurate. Digests of
+/// separate streams can be [merged](TDigest::merge).
+#[derive(Debug, Clone)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<Centroid>,
+    count: f64,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    /// Creates a new `TDigest` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `compression` - Bounds the number of centroids, which is roughly half of it. A
+    ///   higher compression is more accurate and uses more memory. `100` is a good default.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `compression` is zero.
+    pub fn new(compression: u32) -> Result<Self, MetricError> {
+        if compression == 0 {
+            return Err(MetricError::ZeroCapacity);
+        }
+        Ok(Self {
+            compression: f64::from(compression),
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            count: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        })
+    }
+
+    /// Adds a value. Non-finite values are ignored.
+    pub fn add(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        self.push(Centroid {
+            mean: value,
+            weight: 1.0,
+        });
+    }
+
+    /// Adds all values of `other` to this digest.
+    pub fn merge(&mut self, other: &TDigest) {
+        for &centroid in other.centroids.iter().chain(&other.buffer) {
+            self.push(centroid);
+        }
+        // The centroids at the extremes can hold several values, so their means are not
+        // necessarily the extremes of `other`
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Estimates the quantile `q`, e.g. `0.99` for p99.
+    ///
+    /// Returns `None` if no values have been added or if `q` is outside of `0.0..=1.0`.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if !(0.0..=1.0).contains(&q) || self.count == 0.0 {
+            return None;
+        }
+        let merged;
+        let centroids = if self.buffer.is_empty() {
+            &self.centroids
+        } else {
+            let mut digest = self.clone();
+            digest.compress();
+            merged = digest.centroids;
+            &merged
+        };
+
+        let target = q * self.count;
+        let first = centroids[0];
+        if target < first.weight / 2.0 {
+            let fraction = target / (first.weight / 2.0);
+            return Some(self.min + (first.mean - self.min) * fraction);
+        }
+
+        let mut cumulative = 0.0;
+        for pair in centroids.windows(2) {
+            let left_center = cumulative + pair[0].weight / 2.0;
+            let right_center = cumulative + pair[0].weight + pair[1].weight / 2.0;
+            if target <= right_center {
+                let fraction = (target - left_center) / (right_center - left_center);
+                return Some(pair[0].mean + (pair[1].mean - pair[0].mean) * fraction);
+            }
+            cumulative += pair[0].weight;
+        }
+
+        let last = centroids[centroids.len() - 1];
+        let last_center = self.count - last.weight / 2.0;
+        if target <= last_center || last.weight / 2.0 == 0.0 {
+            return Some(last.mean);
+        }
+        let fraction = (target - last_center) / (last.weight / 2.0);
+        Some(last.mean + (self.max - last.mean) * fraction)
+    }
+
+    /// Returns the number of values added.
+    pub fn count(&self) -> u64 {
+        self.count as u64
+    }
+
+    /// Returns the smallest value added, or `None` if no values have been added.
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0.0).then_some(self.min)
+    }
+
+    /// Returns the largest value added, or `None` if no values have been added.
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0.0).then_some(self.max)
+    }
+
+    fn push(&mut self, centroid: Centroid) {
+        self.count += centroid.weight;
+        self.min = self.min.min(centroid.mean);
+        self.max = self.max.max(centroid.mean);
+        self.buffer.push(centroid);
+        if self.buffer.len() >= 5 * self.compression as usize {
+            self.compress();
+        }
+    }
+
+    /// Merges the buffered centroids into the digest, keeping every centroid within the
+    /// size allowed by the `k1` scale function for its quantile.
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all = core::mem::take(&mut self.centroids);
+        all.append(&mut self.buffer);
+        all.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+
+        let mut merged = Vec::with_capacity(self.compression as usize);
+        let mut current = all[0];
+        let mut weight_so_far = 0.0;
+        let mut q_limit = self.q_of_k(self.k_of_q(0.0) + 1.0);
+        for &next in &all[1..] {
+            let q = (weight_so_far + current.weight + next.weight) / self.count;
+            if q <= q_limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_so_far += current.weight;
+                merged.push(current);
+                q_limit = self.q_of_k(self.k_of_q(weight_so_far / self.count) + 1.0);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    fn k_of_q(&self, q: f64) -> f64 {
+        self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin()
+    }
+
+    fn q_of_k(&self, k: f64) -> f64 {
+        ((k * 2.0 * PI / self.compression).sin() + 1.0) / 2.0
+    }
+}
+
+impl Default for TDigest {
+    /// Creates a `TDigest` with a compression of `100`.
+    fn default() -> Self {
+        Self::new(100).expect("100 is not zero")
+    }
+}
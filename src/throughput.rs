@@ -0,0 +1,67 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::{DurationAggregate, MetricError, MinMaxAvg, RateMetric};
+use core::fmt::Display;
+use monotonic_time_rs::{Millis, MillisDuration};
+
+/// Tracks the number of operations per second together with the latency of each operation.
+///
+/// Bundles a [`RateMetric`] with a [`DurationAggregate`].
+#[derive(Debug)]
+pub struct ThroughputMetric {
+    rate: RateMetric,
+    latency: DurationAggregate,
+}
+
+impl ThroughputMetric {
+    /// Creates a new `ThroughputMetric` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The initial [`Millis`] from which the rate is measured.
+    /// * `latency_threshold` - The number of operations that completes a latency window.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `latency_threshold` is zero.
+    pub fn new(now: Millis, latency_threshold: u32) -> Result<Self, MetricError> {
+        Ok(Self {
+            rate: RateMetric::new(now),
+            latency: DurationAggregate::new(latency_threshold)?,
+        })
+    }
+
+    /// Records a completed operation.
+    ///
+    /// The rate is [updated](ThroughputMetric::update) to `now` first, so that the
+    /// operation counts towards the measurement interval it completed in.
+    pub fn record(&mut self, now: Millis, latency: MillisDuration) {
+        self.rate.update(now);
+        self.rate.increment();
+        self.latency.add(latency);
+    }
+
+    /// Closes the measurement interval of the rate, if it has elapsed.
+    pub fn update(&mut self, now: Millis) {
+        self.rate.update(now);
+    }
+
+    /// Returns the number of operations per second.
+    pub fn rate(&self) -> f32 {
+        self.rate.rate()
+    }
+
+    /// Returns the latency in milliseconds of the last completed latency window.
+    pub fn latency(&self) -> Option<MinMaxAvg<u64>> {
+        self.latency.values()
+    }
+}
+
+/// Displays the rate and the latency, e.g. `1000/s, latency min:1ms, avg:2ms, max:9ms`.
+impl Display for ThroughputMetric {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}/s, latency {}", self.rate.rate(), self.latency)
+    }
+}
@@ -0,0 +1,42 @@
+/*
+ * Copyright (c) Peter Bjorklund. All rights reserved. https://github.com/piot/metricator
+ * Licensed under the MIT License. See LICENSE in the project root for license information.
+ */
+use crate::AggregateMetric;
+use monotonic_time_rs::Millis;
+
+/// Measures the time from its creation until it is dropped, and adds the elapsed
+/// milliseconds to an [`AggregateMetric`].
+///
+/// Created by [`AggregateMetric::time`].
+pub struct Timer<'a, F: Fn() -> Millis> {
+    metric: &'a mut AggregateMetric<u64>,
+    now: F,
+    started_at: Millis,
+}
+
+impl<F: Fn() -> Millis> Drop for Timer<'_, F> {
+    fn drop(&mut self) {
+        let elapsed = (self.now)()
+            .checked_duration_since_ms(self.started_at)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        self.metric.add(elapsed);
+    }
+}
+
+impl AggregateMetric<u64> {
+    /// Starts a [`Timer`] that adds the elapsed milliseconds to this metric when dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Returns the current time, e.g. `|| clock.now()` for a
+    ///   [`monotonic_time_rs::MonotonicClock`].
+    pub fn time<F: Fn() -> Millis>(&mut self, now: F) -> Timer<'_, F> {
+        let started_at = now();
+        Timer {
+            metric: self,
+            now,
+            started_at,
+        }
+    }
+}
@@ -3,7 +3,13 @@
  * Licensed under the MIT License. See LICENSE in the project root for license information.
  */
 
-use metricator::{AggregateMetric, MinMaxAvg, RateMetric};
+use metricator::{
+    AggregateMetric, AggregateSnapshot, AtomicRateMetric, BytePrefix, ByteRate, Counter,
+    CounterRate, DecayingAggregate, DurationAggregate, EventRate, EwmaMetric, FixedAggregate,
+    Gauge, Histogram, MetricError, MetricPart, MetricRegistry, MinMaxAvg, MinMaxAvgDelta,
+    MultiRateMetric, P2Quantile, QuantileSummary, RateMetric, RateMetricState, RatioMetric,
+    Reservoir, SlidingAggregate, ThroughputMetric,
+};
 use monotonic_time_rs::{Millis, MillisDuration};
 
 #[test_log::test]
@@ -52,9 +58,11 @@ fn aggregate_int() {
 
 #[test_log::test]
 fn zero_threshold() {
-    let result: Result<AggregateMetric<f32>, String> = AggregateMetric::new(0);
+    let result: Result<AggregateMetric<f32>, MetricError> = AggregateMetric::new(0);
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), "threshold can not be zero".to_string());
+    let error = result.unwrap_err();
+    assert_eq!(error, MetricError::ZeroThreshold);
+    assert_eq!(error.to_string(), "threshold can not be zero");
 }
 
 #[test_log::test]
@@ -94,3 +102,2288 @@ fn all_equal_values_i32() {
     assert_eq!(aggregate.average(), Some(7.0));
     assert_eq!(aggregate.values(), Some(MinMaxAvg::new(7, 7.0, 7)));
 }
+
+#[test_log::test]
+fn peak_rate() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::new(now);
+
+    m.add(20);
+    now += MillisDuration::from_secs(10.0).expect("should be positive");
+    m.update(now);
+    assert_eq!(m.peak_rate(), 2.0);
+
+    m.add(10);
+    now += MillisDuration::from_secs(10.0).expect("should be positive");
+    m.update(now);
+    assert_eq!(m.rate(), 1.0);
+    assert_eq!(m.peak_rate(), 2.0);
+
+    m.add(100);
+    now += MillisDuration::from_millis(100);
+    m.update(now);
+    assert_eq!(m.peak_rate(), 2.0);
+
+    m.reset_peak();
+    assert_eq!(m.peak_rate(), 0.0);
+}
+
+#[test_log::test]
+fn smoothed_rate() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::with_smoothing(now, 1.0, 0.5).expect("alpha should be valid");
+
+    m.add(10);
+    now += MillisDuration::from_secs(1.0).expect("should be positive");
+    m.update(now);
+    assert_eq!(m.rate(), 5.0);
+
+    m.add(10);
+    now += MillisDuration::from_secs(1.0).expect("should be positive");
+    m.update(now);
+    assert_eq!(m.rate(), 7.5);
+}
+
+#[test_log::test]
+fn invalid_smoothing_alpha() {
+    let now = Millis::new(0);
+    assert!(RateMetric::with_smoothing(now, 1.0, 1.5).is_err());
+    assert!(RateMetric::with_smoothing(now, 1.0, -0.1).is_err());
+    assert!(RateMetric::with_smoothing(now, 1.0, f32::NAN).is_err());
+}
+
+#[test_log::test]
+fn pending_count() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::new(now);
+
+    m.add(12);
+    m.increment();
+    assert_eq!(m.pending_count(), 13);
+
+    now += MillisDuration::from_secs(1.0).expect("should be positive");
+    m.update(now);
+    assert_eq!(m.pending_count(), 0);
+}
+
+#[test_log::test]
+fn decrement_saturates() {
+    let mut m = RateMetric::new(Millis::new(0));
+
+    m.add(3);
+    m.decrement();
+    assert_eq!(m.pending_count(), 2);
+
+    m.sub(10);
+    assert_eq!(m.pending_count(), 0);
+
+    m.decrement();
+    assert_eq!(m.pending_count(), 0);
+}
+
+#[test_log::test]
+fn update_checked() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::new(now);
+
+    m.add(5);
+    now += MillisDuration::from_millis(100);
+    assert!(!m.update_checked(now));
+
+    now += MillisDuration::from_millis(400);
+    assert!(m.update_checked(now));
+    assert_eq!(m.rate(), 10.0);
+}
+
+#[test_log::test]
+fn estimated_rate() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::new(now);
+
+    m.add(5);
+    assert_eq!(m.estimated_rate(now), 0.0);
+
+    now += MillisDuration::from_millis(1);
+    assert_eq!(m.estimated_rate(now), 0.0);
+
+    now += MillisDuration::from_millis(249);
+    assert_eq!(m.estimated_rate(now), 20.0);
+    assert_eq!(m.rate(), 0.0);
+}
+
+#[test_log::test]
+fn weighted_rate() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::with_interval(now, 1.0).expect("interval should be valid");
+
+    m.add_weighted(0.5);
+    m.add_weighted(0.5);
+    m.add_weighted(-1.0);
+    m.add_weighted(f32::NAN);
+    m.increment();
+
+    now += MillisDuration::from_secs(1.0).expect("should be positive");
+    m.update(now);
+
+    assert_eq!(m.rate(), 2.0);
+}
+
+#[test_log::test]
+fn shrink_interval() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::with_interval(now, 10.0).expect("interval should be valid");
+    assert_eq!(m.interval(), MillisDuration::from_millis(10_000));
+
+    m.add(4);
+    now += MillisDuration::from_secs(2.0).expect("should be positive");
+    assert!(!m.update_checked(now));
+    assert_eq!(m.pending_count(), 4);
+
+    m.set_interval(MillisDuration::from_secs(1.0).expect("should be positive"))
+        .expect("interval should be valid");
+    assert!(m.update_checked(now));
+    assert_eq!(m.rate(), 2.0);
+}
+
+#[test_log::test]
+fn multi_rate() {
+    let mut now = Millis::new(0);
+    let mut m = MultiRateMetric::new(
+        now,
+        &[
+            MillisDuration::from_millis(1_000),
+            MillisDuration::from_millis(10_000),
+        ],
+    )
+    .expect("intervals should be valid");
+
+    m.add(10);
+    now += MillisDuration::from_millis(1_000);
+    m.update(now);
+    assert_eq!(m.rates(), vec![10.0, 0.0]);
+
+    m.add(20);
+    now += MillisDuration::from_millis(9_000);
+    m.update(now);
+    assert_eq!(m.rate(0), Some(20.0 / 9.0));
+    assert_eq!(m.rate(1), Some(3.0));
+    assert_eq!(m.rate(2), None);
+}
+
+#[test_log::test]
+fn backwards_time() {
+    let mut now = Millis::new(10_000);
+    let mut m = RateMetric::new(now);
+
+    m.add(10);
+    now += MillisDuration::from_secs(1.0).expect("should be positive");
+    m.update(now);
+    assert_eq!(m.rate(), 10.0);
+
+    m.add(10);
+    assert!(!m.update_checked(Millis::new(5_000)));
+    assert_eq!(m.rate(), 10.0);
+    assert_eq!(m.pending_count(), 10);
+}
+
+#[test_log::test]
+fn rate_display() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::new(now).with_unit("pkt");
+    assert_eq!(m.to_string(), "rate:0pkt/s");
+
+    m.add(3);
+    now += MillisDuration::from_secs(2.0).expect("should be positive");
+    m.update(now);
+    assert_eq!(m.to_string(), "rate:1.5pkt/s");
+    assert_eq!(RateMetric::new(now).to_string(), "rate:0/s");
+}
+
+#[test_log::test]
+fn lifetime_total() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::new(now);
+
+    m.add(10);
+    m.increment();
+    now += MillisDuration::from_secs(1.0).expect("should be positive");
+    m.update(now);
+    assert_eq!(m.pending_count(), 0);
+
+    m.add(u32::MAX);
+    assert_eq!(m.total(), u64::from(u32::MAX) + 11);
+}
+
+#[test_log::test]
+fn median() {
+    let mut aggregate = AggregateMetric::new_with_median(3).expect("should not be zero");
+
+    aggregate.add(9);
+    aggregate.add(1);
+    assert_eq!(aggregate.median(), None);
+
+    aggregate.add(4);
+    assert_eq!(aggregate.median(), Some(4.0));
+}
+
+#[test_log::test]
+fn median_even_count() {
+    let mut aggregate = AggregateMetric::new_with_median(4).expect("should not be zero");
+
+    for value in [10.0, 1.0, 3.0, 2.0] {
+        aggregate.add(value);
+    }
+    assert_eq!(aggregate.median(), Some(2.5));
+
+    let mut plain = AggregateMetric::new(1).expect("should not be zero");
+    plain.add(1.0);
+    assert_eq!(plain.median(), None);
+}
+
+#[test_log::test]
+fn percentile() {
+    let mut aggregate = AggregateMetric::new_buffered(10).expect("should not be zero");
+
+    for value in (1..=10).rev() {
+        aggregate.add(value);
+    }
+
+    assert_eq!(aggregate.percentile(0.0), Some(1));
+    assert_eq!(aggregate.percentile(50.0), Some(5));
+    assert_eq!(aggregate.percentile(95.0), Some(10));
+    assert_eq!(aggregate.percentile(100.0), Some(10));
+    assert_eq!(aggregate.percentile(101.0), None);
+    assert_eq!(aggregate.percentile(-1.0), None);
+}
+
+#[test_log::test]
+fn variance() {
+    let mut aggregate = AggregateMetric::new(4).expect("should not be zero");
+
+    aggregate.add(2);
+    aggregate.add(4);
+    aggregate.add(4);
+    assert_eq!(aggregate.variance(), None);
+    assert_eq!(aggregate.std_dev(), None);
+
+    aggregate.add(6);
+    assert_eq!(aggregate.variance(), Some(2.0));
+    assert_eq!(aggregate.std_dev(), Some(2.0_f32.sqrt()));
+}
+
+#[test_log::test]
+fn variance_of_equal_values() {
+    let mut aggregate = AggregateMetric::new(3).expect("should not be zero");
+
+    aggregate.add(0.1);
+    aggregate.add(0.1);
+    aggregate.add(0.1);
+
+    assert!(aggregate.variance().expect("window should be complete") < 1e-6);
+}
+
+#[test_log::test]
+fn large_threshold() {
+    let mut aggregate = AggregateMetric::new(1000).expect("should not be zero");
+
+    for _ in 0..999 {
+        aggregate.add(1);
+    }
+    assert_eq!(aggregate.average(), None);
+
+    aggregate.add(1001);
+    assert_eq!(aggregate.average(), Some(2.0));
+}
+
+#[test_log::test]
+fn sum_overflow_saturates() {
+    let mut aggregate = AggregateMetric::<u8>::new(3).expect("should not be zero");
+
+    aggregate.add(u8::MAX - 5);
+    assert_eq!(aggregate.try_add(10), Err(MetricError::Overflow));
+    aggregate.add(10);
+    aggregate.add(u8::MAX);
+
+    let values = aggregate.values().expect("should calculate values");
+    assert!((values.avg - 515.0 / 3.0).abs() < 1e-3, "{}", values.avg);
+    assert_eq!(values.min, 10);
+    assert_eq!(values.max, u8::MAX);
+
+    // Only the sum saturates, the statistics use the true values
+    aggregate.add(200);
+    aggregate.add(200);
+    assert_eq!(aggregate.current_sum(), u8::MAX);
+    aggregate.add(200);
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(200, 200.0, 200)));
+    assert_eq!(aggregate.variance(), Some(0.0));
+    assert_eq!(
+        aggregate.snapshot().expect("should have snapshot").sum,
+        u8::MAX
+    );
+}
+
+#[test_log::test]
+fn sum_overflow_negative() {
+    let mut aggregate = AggregateMetric::<i32>::new(2).expect("should not be zero");
+
+    aggregate.add(i32::MIN + 1);
+    assert!(aggregate.try_add(-2).is_err());
+    assert!(aggregate.try_add(i32::MAX).is_ok());
+    assert_eq!(aggregate.average(), Some(0.0));
+}
+
+#[test_log::test]
+fn aggregate_pending_count() {
+    let mut aggregate = AggregateMetric::new(10).expect("should not be zero");
+    assert_eq!(aggregate.threshold(), 10);
+
+    for value in 0..7 {
+        aggregate.add(value);
+    }
+    assert_eq!(aggregate.pending_count(), 7);
+
+    for value in 0..3 {
+        aggregate.add(value);
+    }
+    assert_eq!(aggregate.pending_count(), 0);
+}
+
+#[test_log::test]
+fn reset_discards_pending() {
+    let mut aggregate = AggregateMetric::new(3).expect("should not be zero");
+
+    aggregate.add(100);
+    aggregate.add(100);
+    aggregate.reset();
+    assert_eq!(aggregate.pending_count(), 0);
+
+    aggregate.add(1);
+    aggregate.add(2);
+    aggregate.add(3);
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(1, 2.0, 3)));
+
+    aggregate.add(4);
+    aggregate.reset();
+    assert_eq!(aggregate.pending_count(), 0);
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(1, 2.0, 3)));
+
+    aggregate.clear();
+    assert_eq!(aggregate.values(), None);
+    assert_eq!(aggregate.snapshot(), None);
+}
+
+#[test_log::test]
+fn reset_keep_last() {
+    let mut aggregate = AggregateMetric::new(2).expect("should not be zero");
+
+    aggregate.add(1);
+    aggregate.add(3);
+    aggregate.add(50);
+    aggregate.reset_keep_last();
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(1, 2.0, 3)));
+
+    aggregate.add(5);
+    aggregate.add(7);
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(5, 6.0, 7)));
+}
+
+#[test_log::test]
+fn merge_half_full_windows() {
+    let mut first = AggregateMetric::new(4).expect("should not be zero");
+    let mut second = AggregateMetric::new(4).expect("should not be zero");
+
+    first.add(1);
+    first.add(3);
+    second.add(8);
+    second.add(4);
+
+    first.merge(&second).expect("metrics should be compatible");
+
+    assert_eq!(first.values(), Some(MinMaxAvg::new(1, 4.0, 8)));
+    assert_eq!(first.pending_count(), 0);
+    assert_eq!(second.pending_count(), 2);
+}
+
+#[test_log::test]
+fn merge_below_threshold() {
+    let mut first = AggregateMetric::new(10).expect("should not be zero");
+    let mut second = AggregateMetric::new(10).expect("should not be zero");
+
+    first.add(1.0);
+    second.add(2.0);
+    first.merge(&second).expect("metrics should be compatible");
+
+    assert_eq!(first.values(), None);
+    assert_eq!(first.pending_count(), 2);
+}
+
+#[test_log::test]
+fn partial_values() {
+    let mut aggregate = AggregateMetric::new(4).expect("should not be zero");
+    assert_eq!(aggregate.partial_values(), None);
+
+    aggregate.add(6);
+    aggregate.add(2);
+    assert_eq!(aggregate.partial_values(), Some(MinMaxAvg::new(2, 4.0, 6)));
+    assert_eq!(aggregate.values(), None);
+
+    aggregate.add(1);
+    aggregate.add(3);
+    assert_eq!(aggregate.partial_values(), None);
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(1, 3.0, 6)));
+}
+
+#[test_log::test]
+fn time_window() {
+    let mut now = Millis::new(0);
+    assert_eq!(
+        AggregateMetric::<u32>::with_time_window(now, MillisDuration::from_millis(0)).err(),
+        Some(MetricError::InvalidInterval)
+    );
+    let mut aggregate = AggregateMetric::with_time_window(now, MillisDuration::from_millis(1_000))
+        .expect("window should be valid");
+
+    aggregate.add_at(now, 4);
+    now += MillisDuration::from_millis(500);
+    aggregate.add_at(now, 8);
+    assert_eq!(aggregate.values(), None);
+
+    now += MillisDuration::from_millis(500);
+    aggregate.add_at(now, 100);
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(4, 6.0, 8)));
+    assert_eq!(aggregate.pending_count(), 1);
+
+    now += MillisDuration::from_millis(5_000);
+    aggregate.add_at(now, 1);
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(100, 100.0, 100)));
+}
+
+#[test_log::test]
+fn sliding_aggregate() {
+    let mut sliding = SlidingAggregate::new(3).expect("should not be zero");
+    assert_eq!(sliding.values(), None);
+
+    sliding.add(10);
+    assert_eq!(sliding.values(), Some(MinMaxAvg::new(10, 10.0, 10)));
+
+    sliding.add(2);
+    sliding.add(6);
+    assert_eq!(sliding.values(), Some(MinMaxAvg::new(2, 6.0, 10)));
+
+    sliding.add(4);
+    assert_eq!(sliding.len(), 3);
+    assert_eq!(sliding.values(), Some(MinMaxAvg::new(2, 4.0, 6)));
+}
+
+#[test_log::test]
+fn histogram() {
+    let mut histogram = Histogram::new(&[10, 50, 100, 500]).expect("should be increasing");
+
+    for value in [1, 10, 11, 50, 99, 500, 501, 10_000] {
+        histogram.record(value);
+    }
+
+    assert_eq!(histogram.buckets(), &[2, 2, 1, 1, 2]);
+    assert_eq!(histogram.count(), 8);
+    assert_eq!(
+        histogram.to_string(),
+        "bucket<=10: 2\nbucket<=50: 2\nbucket<=100: 1\nbucket<=500: 1\nbucket>500: 2"
+    );
+}
+
+#[test_log::test]
+fn histogram_unsorted_boundaries() {
+    assert!(Histogram::new(&[10, 5]).is_err());
+    assert!(Histogram::new(&[10, 10]).is_err());
+}
+
+#[test_log::test]
+fn non_finite_values() {
+    let mut aggregate = AggregateMetric::new(3).expect("should not be zero");
+
+    aggregate.add(1.0);
+    assert_eq!(aggregate.try_add(f32::NAN), Err(MetricError::NonFinite));
+    assert!(aggregate.try_add(f32::INFINITY).is_err());
+    aggregate.add(f32::NAN);
+    aggregate.add(f32::NEG_INFINITY);
+    aggregate.add(2.0);
+    assert_eq!(aggregate.pending_count(), 2);
+
+    aggregate.add(3.0);
+    let average = aggregate.average().expect("window should be complete");
+    assert!(average.is_finite());
+    assert_eq!(average, 2.0);
+}
+
+#[cfg(feature = "serde")]
+#[test_log::test]
+fn min_max_avg_serde_round_trip() {
+    let values = MinMaxAvg::new(2, 5.0, 8).with_unit("ms");
+
+    let json = serde_json::to_string(&values).expect("should serialize");
+    assert_eq!(json, r#"{"min":2,"avg":5.0,"max":8,"unit":"ms"}"#);
+
+    let deserialized: MinMaxAvg<i32> = serde_json::from_str(&json).expect("should deserialize");
+    assert_eq!(deserialized, MinMaxAvg::new(2, 5.0, 8));
+    assert_eq!(deserialized.with_unit("ms"), values);
+}
+
+#[cfg(feature = "serde")]
+#[test_log::test]
+fn aggregate_serde_round_trip() {
+    let mut aggregate = AggregateMetric::new_buffered(4)
+        .expect("should not be zero")
+        .with_unit("ms");
+    aggregate.add(3);
+    aggregate.add(9);
+
+    let json = serde_json::to_string(&aggregate).expect("should serialize");
+    let mut restored: AggregateMetric<i32> =
+        serde_json::from_str(&json).expect("should deserialize");
+    restored = restored.with_unit("ms");
+
+    for value in [1, 7] {
+        aggregate.add(value);
+        restored.add(value);
+    }
+
+    assert_eq!(restored.values(), aggregate.values());
+    assert_eq!(
+        restored.values(),
+        Some(MinMaxAvg::new(1, 5.0, 9).with_unit("ms"))
+    );
+    assert_eq!(restored.median(), aggregate.median());
+    assert_eq!(restored.variance(), aggregate.variance());
+}
+
+#[test_log::test]
+fn min_max_avg_range() {
+    let values = MinMaxAvg::new(2, 5.0, 8).with_unit("ms");
+
+    assert_eq!(values.range(), 6);
+    assert_eq!(
+        values.to_string_with_range(),
+        "min:2ms, avg:5ms, max:8ms, range:6ms"
+    );
+    assert_eq!(MinMaxAvg::new(-1.5, 0.0, 1.5).range(), 3.0);
+}
+
+#[test_log::test]
+fn min_max_avg_tuple() {
+    let values = MinMaxAvg::new(2, 5.0, 8).with_unit("ms");
+    assert_eq!(values.as_tuple(), (2, 5.0, 8));
+
+    let (min, avg, max): (i32, f32, i32) = values.into();
+    assert_eq!((min, avg, max), (2, 5.0, 8));
+}
+
+#[test_log::test]
+fn min_max_avg_copy() {
+    let values = MinMaxAvg::new(2, 5.0, 8).with_unit("ms");
+    let history = [values; 4];
+
+    assert_eq!(history[3], values);
+    assert_eq!(history[0].unit, "ms");
+}
+
+#[test_log::test]
+fn metric_error_is_std_error() {
+    fn build() -> Result<AggregateMetric<u32>, Box<dyn std::error::Error>> {
+        Ok(AggregateMetric::new(0)?)
+    }
+
+    let error = build().expect_err("zero threshold should fail");
+    assert_eq!(error.to_string(), "threshold can not be zero");
+    assert_eq!(
+        RateMetric::with_smoothing(Millis::new(0), 1.0, 2.0).unwrap_err(),
+        MetricError::InvalidAlpha
+    );
+    assert_eq!(
+        SlidingAggregate::<u32>::new(0).unwrap_err(),
+        MetricError::ZeroCapacity
+    );
+}
+
+#[test_log::test]
+fn add_slice_matches_add() {
+    let samples = [4, 8, 15, 16, 23, 42, 7, 1];
+    let mut batched = AggregateMetric::new(3).expect("should not be zero");
+    let mut looped = AggregateMetric::new(3).expect("should not be zero");
+
+    batched.add_slice(&samples);
+    for value in samples {
+        looped.add(value);
+    }
+
+    assert_eq!(batched.values(), looped.values());
+    assert_eq!(batched.values(), Some(MinMaxAvg::new(16, 27.0, 42)));
+    assert_eq!(batched.pending_count(), looped.pending_count());
+    assert_eq!(batched.partial_values(), looped.partial_values());
+}
+
+#[test_log::test]
+fn collect_into_aggregate() {
+    let aggregate: AggregateMetric<i32> = vec![3, 1, 8].into_iter().collect();
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(1, 4.0, 8)));
+
+    let empty: AggregateMetric<i32> = Vec::new().into_iter().collect();
+    assert_eq!(empty.values(), None);
+
+    // Non-finite values don't count towards the threshold
+    let floats: AggregateMetric<f32> = vec![1.0, f32::NAN, 2.0, f32::INFINITY]
+        .into_iter()
+        .collect();
+    assert_eq!(floats.values(), Some(MinMaxAvg::new(1.0, 1.5, 2.0)));
+    assert_eq!(floats.pending_count(), 0);
+    assert_eq!(floats.rejected_count(), 2);
+}
+
+#[test_log::test]
+fn extend_aggregate() {
+    let mut aggregate =
+        AggregateMetric::from_iter_with_threshold([1, 2], 4).expect("should not be zero");
+    assert_eq!(aggregate.pending_count(), 2);
+
+    aggregate.extend([3, 4, 5]);
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(1, 2.5, 4)));
+    assert_eq!(aggregate.pending_count(), 1);
+}
+
+#[test_log::test]
+fn current_min_max() {
+    let mut aggregate = AggregateMetric::new(3).expect("should not be zero");
+    assert_eq!(aggregate.current_min(), None);
+    assert_eq!(aggregate.current_max(), None);
+
+    aggregate.add(5);
+    aggregate.add(-2);
+    assert_eq!(aggregate.current_min(), Some(-2));
+    assert_eq!(aggregate.current_max(), Some(5));
+
+    aggregate.add(0);
+    assert_eq!(aggregate.current_min(), None);
+    assert_eq!(aggregate.current_max(), None);
+}
+
+#[test_log::test]
+fn current_sum() {
+    let mut aggregate = AggregateMetric::new(3).expect("should not be zero");
+    assert_eq!(aggregate.current_sum(), 0);
+
+    aggregate.add(5);
+    aggregate.add(7);
+    assert_eq!(aggregate.current_sum(), 12);
+    assert_eq!(aggregate.pending_count(), 2);
+
+    aggregate.add(1);
+    assert_eq!(aggregate.current_sum(), 0);
+}
+
+#[test_log::test]
+fn min_max_avg_precision() {
+    let values = MinMaxAvg::new(1, 10.0 / 3.0, 5).with_unit("ms");
+    assert_eq!(values.to_string(), "min:1ms, avg:3.3333333ms, max:5ms");
+    assert_eq!(
+        values.with_precision(2).to_string(),
+        "min:1ms, avg:3.33ms, max:5ms"
+    );
+
+    let floats = MinMaxAvg::new(0.125, 0.5, 1.0).with_precision(1);
+    assert_eq!(floats.to_string(), "min:0.1, avg:0.5, max:1.0");
+
+    // The display precision is not part of the values
+    assert_eq!(floats.values, MinMaxAvg::new(0.125, 0.5, 1.0));
+    assert_eq!(
+        MinMaxAvg::new(0.125, 0.5, 1.0).partial_cmp(&floats.values),
+        Some(std::cmp::Ordering::Equal)
+    );
+}
+
+#[test_log::test]
+fn geometric_mean() {
+    let mut aggregate = AggregateMetric::new_geometric(3).expect("should not be zero");
+
+    aggregate.add(1.0);
+    assert_eq!(aggregate.try_add(0.0), Err(MetricError::NonPositive));
+    aggregate.add(-4.0);
+    aggregate.add(2.0);
+    aggregate.add(4.0);
+
+    let values = aggregate.values().expect("window should be complete");
+    assert!((values.avg - 2.0).abs() < 1e-6);
+    assert_eq!(values.min, 1.0);
+    assert_eq!(values.max, 4.0);
+}
+
+#[test_log::test]
+fn harmonic_mean() {
+    let mut aggregate = AggregateMetric::new_harmonic(3).expect("should not be zero");
+
+    aggregate.add(1.0);
+    assert_eq!(aggregate.try_add(0.0), Err(MetricError::Zero));
+    aggregate.add(0.0);
+    aggregate.add(4.0);
+    aggregate.add(4.0);
+
+    assert_eq!(aggregate.average(), Some(2.0));
+}
+
+#[test_log::test]
+fn trimmed_mean() {
+    let mut aggregate = AggregateMetric::new_trimmed(10, 0.1).expect("should be valid");
+
+    aggregate.add_slice(&[1000, 5, 5, 5, 5, 5, 5, 5, 5, 0]);
+
+    let values = aggregate.values().expect("window should be complete");
+    assert_eq!(values.avg, 5.0);
+    assert_eq!(values.min, 0);
+    assert_eq!(values.max, 1000);
+    assert_eq!(aggregate.median(), Some(5.0));
+}
+
+#[test_log::test]
+fn invalid_trim_fraction() {
+    assert_eq!(
+        AggregateMetric::<u32>::new_trimmed(10, 0.5).unwrap_err(),
+        MetricError::InvalidTrimFraction
+    );
+    assert!(AggregateMetric::<u32>::new_trimmed(10, -0.1).is_err());
+    assert!(AggregateMetric::<u32>::new_trimmed(0, 0.1).is_err());
+}
+
+#[test_log::test]
+fn ewma() {
+    let mut ewma = EwmaMetric::new(0.25).expect("alpha should be valid");
+    assert_eq!(ewma.value(), None);
+
+    ewma.update(8.0);
+    assert_eq!(ewma.value(), Some(8.0));
+
+    ewma.update(4.0);
+    assert_eq!(ewma.value(), Some(7.0));
+
+    assert_eq!(EwmaMetric::new(1.1).unwrap_err(), MetricError::InvalidAlpha);
+}
+
+#[test_log::test]
+fn gauge() {
+    let mut gauge = Gauge::new().with_unit("ms");
+    assert_eq!(gauge.value(), None);
+    assert_eq!(gauge.to_string(), "value:none");
+
+    gauge.set(42);
+    gauge.set(10);
+    gauge.set(90);
+    gauge.set(42);
+
+    assert_eq!(gauge.value(), Some(42));
+    assert_eq!(gauge.min(), Some(10));
+    assert_eq!(gauge.max(), Some(90));
+    assert_eq!(gauge.to_string(), "value:42ms (min:10ms max:90ms)");
+}
+
+#[test_log::test]
+fn counter_delta() {
+    let mut counter = Counter::new();
+    counter.inc();
+    counter.add(9);
+    assert_eq!(counter.value(), 10);
+
+    let previous = counter.value();
+    counter.add(5);
+    assert_eq!(counter.delta_since(previous), 5);
+}
+
+#[test_log::test]
+fn counter_wraparound() {
+    let mut counter = Counter::new();
+    counter.add(u64::MAX - 1);
+    let previous = counter.value();
+
+    counter.add(4);
+    assert_eq!(counter.value(), 2);
+    assert_eq!(counter.delta_since(previous), 4);
+}
+
+#[test_log::test]
+fn scoped_timer() {
+    let now = std::cell::Cell::new(Millis::new(1_000));
+    let mut metric = AggregateMetric::<u64>::new(2).expect("should not be zero");
+
+    {
+        let _timer = metric.time(|| now.get());
+        now.set(Millis::new(1_040));
+    }
+    {
+        let _timer = metric.time(|| now.get());
+        now.set(Millis::new(1_100));
+    }
+
+    assert_eq!(metric.values(), Some(MinMaxAvg::new(40, 50.0, 60)));
+}
+
+#[test_log::test]
+fn registry() {
+    let mut now = Millis::new(0);
+    let mut registry = MetricRegistry::new(now, 2).expect("should not be zero");
+
+    registry.rate("packets").add(10);
+    registry.aggregate("latency").add(1.0);
+    registry.aggregate("latency").add(3.0);
+    registry.aggregate("jitter").add(1.0);
+
+    now += MillisDuration::from_secs(1.0).expect("should be positive");
+    registry.update_all(now);
+    assert_eq!(registry.rate("packets").rate(), 10.0);
+
+    let dump: Vec<String> = registry
+        .iter()
+        .map(|(name, metric)| format!("{name} {metric}"))
+        .collect();
+    assert_eq!(
+        dump,
+        vec![
+            "packets rate:10/s",
+            "jitter no data",
+            "latency min:1, avg:2, max:3",
+        ]
+    );
+}
+
+#[cfg(feature = "prometheus")]
+#[test_log::test]
+fn prometheus_registry() {
+    let mut now = Millis::new(0);
+    let mut registry = MetricRegistry::new(now, 2).expect("should not be zero");
+
+    registry.rate("packets").add(5);
+    registry.aggregate("latency").add(1.0);
+    registry.aggregate("latency").add(2.0);
+    registry.aggregate("idle").add(1.0);
+    now += MillisDuration::from_secs(2.0).expect("should be positive");
+    registry.update_all(now);
+
+    assert_eq!(
+        metricator::prometheus::render_registry(&registry),
+        "# HELP packets packets per second
+# TYPE packets gauge
+packets 2.5
+# HELP latency_min latency (minimum)
+# TYPE latency_min gauge
+latency_min 1
+# HELP latency_avg latency (average)
+# TYPE latency_avg gauge
+latency_avg 1.5
+# HELP latency_max latency (maximum)
+# TYPE latency_max gauge
+latency_max 2
+"
+    );
+}
+
+#[cfg(feature = "prometheus")]
+#[test_log::test]
+fn prometheus_labels() {
+    let rate = RateMetric::new(Millis::new(0));
+    let mut out = String::new();
+
+    metricator::prometheus::write_rate(
+        &mut out,
+        "requests",
+        "Requests per second",
+        &[("region", "eu"), ("path", "/\"quoted\"")],
+        &rate,
+    );
+
+    assert_eq!(
+        out,
+        "# HELP requests Requests per second
+# TYPE requests gauge
+requests{region=\"eu\",path=\"/\\\"quoted\\\"\"} 0
+"
+    );
+}
+
+#[cfg(feature = "prometheus")]
+#[test_log::test]
+fn prometheus_integer_aggregate() {
+    let mut aggregate = AggregateMetric::<u64>::new(2).expect("should not be zero");
+    aggregate.add(3);
+    aggregate.add(8);
+    let mut out = String::new();
+
+    metricator::prometheus::write_aggregate(&mut out, "frame", "Frame time", &[], &aggregate);
+
+    assert_eq!(
+        out,
+        "# HELP frame_min Frame time (minimum)
+# TYPE frame_min gauge
+frame_min 3
+# HELP frame_avg Frame time (average)
+# TYPE frame_avg gauge
+frame_avg 5.5
+# HELP frame_max Frame time (maximum)
+# TYPE frame_max gauge
+frame_max 8
+"
+    );
+}
+
+#[cfg(feature = "statsd")]
+#[test_log::test]
+fn statsd_lines() {
+    use metricator::statsd;
+
+    assert_eq!(statsd::gauge("players", 1.5), "players:1.5|g");
+    assert_eq!(statsd::counter("packets", 42), "packets:42|c");
+    assert_eq!(
+        statsd::timing("frame", MillisDuration::from_millis(16)),
+        "frame:16|ms"
+    );
+}
+
+#[test_log::test]
+fn atomic_rate_from_threads() {
+    let mut now = Millis::new(0);
+    let mut m = AtomicRateMetric::new(now);
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..250 {
+                    m.increment();
+                }
+            });
+        }
+    });
+    m.add(1000);
+
+    now += MillisDuration::from_secs(1.0).expect("should be positive");
+    m.update(now);
+
+    assert_eq!(m.rate(), 2000.0);
+}
+
+#[test_log::test]
+fn aggregate_snapshot() {
+    let mut aggregate = AggregateMetric::new(3)
+        .expect("should not be zero")
+        .with_unit("ms");
+    assert_eq!(aggregate.snapshot(), None);
+
+    aggregate.add_slice(&[2, 4, 9, 100]);
+
+    assert_eq!(
+        aggregate.snapshot(),
+        Some(AggregateSnapshot {
+            min: 2,
+            avg: 5.0,
+            max: 9,
+            count: 3,
+            sum: 15,
+            unit: "ms",
+        })
+    );
+}
+
+#[test_log::test]
+fn coefficient_of_variation() {
+    let mut aggregate = AggregateMetric::new(4).expect("should not be zero");
+    assert_eq!(aggregate.coefficient_of_variation(), None);
+
+    aggregate.add_slice(&[2, 4, 4, 6]);
+    assert_eq!(
+        aggregate.coefficient_of_variation(),
+        Some(2.0_f32.sqrt() / 4.0)
+    );
+
+    let mut zero_mean = AggregateMetric::new(2).expect("should not be zero");
+    zero_mean.add_slice(&[-1, 1]);
+    assert_eq!(zero_mean.coefficient_of_variation(), None);
+}
+
+#[test_log::test]
+fn rate_per_duration() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::new(now);
+
+    m.add(120);
+    now += MillisDuration::from_secs(1.0).expect("should be positive");
+    m.update(now);
+
+    assert_eq!(m.rate_per(MillisDuration::from_millis(60_000)), 7200.0);
+    assert_eq!(m.rate_per(MillisDuration::from_millis(500)), 60.0);
+}
+
+#[test_log::test]
+fn mode() {
+    let mut aggregate = AggregateMetric::new_with_mode(7).expect("should not be zero");
+    aggregate.add_slice(&[3, 1, 3, 2, 1, 0, 5]);
+    assert_eq!(aggregate.mode(), Some(1));
+
+    aggregate.add_slice(&[4, 4, 4, 2, 2, 9, 9]);
+    assert_eq!(aggregate.mode(), Some(4));
+
+    let mut unbuffered = AggregateMetric::new(1).expect("should not be zero");
+    unbuffered.add(1);
+    assert_eq!(unbuffered.mode(), None);
+}
+
+#[test_log::test]
+fn min_max_avg_ordering() {
+    let mut results = vec![
+        MinMaxAvg::new(1, 5.0, 9),
+        MinMaxAvg::new(1, 2.0, 3),
+        MinMaxAvg::new(2, 5.0, 9),
+        MinMaxAvg::new(0, 5.0, 10),
+    ];
+
+    results.sort_by(|a, b| a.partial_cmp(b).expect("should be comparable"));
+
+    assert_eq!(
+        results,
+        vec![
+            MinMaxAvg::new(1, 2.0, 3),
+            MinMaxAvg::new(1, 5.0, 9),
+            MinMaxAvg::new(2, 5.0, 9),
+            MinMaxAvg::new(0, 5.0, 10),
+        ]
+    );
+    assert_eq!(
+        MinMaxAvg::new(1, f32::NAN, 2).partial_cmp(&MinMaxAvg::new(1, 1.0, 2)),
+        None
+    );
+}
+
+#[test_log::test]
+fn rate_builder() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::builder()
+        .interval(MillisDuration::from_millis(2_000))
+        .smoothing(0.5)
+        .unit("pkt")
+        .build(now)
+        .expect("alpha should be valid");
+    assert_eq!(m.interval(), MillisDuration::from_millis(2_000));
+
+    m.add(8);
+    now += MillisDuration::from_millis(2_000);
+    m.update(now);
+    assert_eq!(m.to_string(), "rate:2pkt/s");
+
+    assert_eq!(
+        RateMetric::builder()
+            .smoothing(-1.0)
+            .build(now)
+            .unwrap_err(),
+        MetricError::InvalidAlpha
+    );
+}
+
+#[test_log::test]
+fn min_max_avg_diff() {
+    let previous = MinMaxAvg::new(2, 5.0, 9).with_unit("ms");
+    let current = MinMaxAvg::new(3, 7.5, 6).with_unit("ms");
+
+    let delta = current.diff(&previous);
+    assert_eq!(
+        delta,
+        MinMaxAvgDelta {
+            min: 1.0,
+            avg: 2.5,
+            max: -3.0,
+            unit: "ms",
+        }
+    );
+    assert_eq!(delta.to_string(), "min:+1ms, avg:+2.5ms, max:-3ms");
+
+    // Unsigned values that decrease give a negative delta instead of overflowing
+    let previous = MinMaxAvg::new(20_u64, 25.0, 40).with_unit("ms");
+    let current = MinMaxAvg::new(5_u64, 10.0, 30).with_unit("ms");
+    let delta = current.diff(&previous);
+    assert_eq!((delta.min, delta.max), (-15.0, -10.0));
+    assert_eq!(delta.to_string(), "min:-15ms, avg:-15ms, max:-10ms");
+}
+
+#[test_log::test]
+fn p2_quantile() {
+    let mut median = P2Quantile::new(0.5).expect("should be a valid quantile");
+    let mut p99 = P2Quantile::new(0.99).expect("should be a valid quantile");
+    assert_eq!(median.quantile(), None);
+
+    // Visits 1..=10_000 in a scrambled order
+    for index in 0..10_000_u32 {
+        let value = (index * 7_919 % 10_000 + 1) as f32;
+        median.observe(value);
+        p99.observe(value);
+    }
+
+    let estimated_median = median.quantile().expect("should have observed values");
+    assert!(
+        (estimated_median - 5_000.0).abs() < 100.0,
+        "{estimated_median}"
+    );
+    let estimated_p99 = p99.quantile().expect("should have observed values");
+    assert!((estimated_p99 - 9_900.0).abs() < 50.0, "{estimated_p99}");
+}
+
+#[test_log::test]
+fn p2_quantile_few_values() {
+    let mut median = P2Quantile::new(0.5).expect("should be a valid quantile");
+    median.observe(3.0);
+    median.observe(1.0);
+    median.observe(2.0);
+    assert_eq!(median.quantile(), Some(2.0));
+
+    assert_eq!(
+        P2Quantile::new(1.0).unwrap_err(),
+        MetricError::InvalidQuantile
+    );
+}
+
+#[test_log::test]
+fn reservoir_percentiles() {
+    let mut reservoir = Reservoir::with_seed(500, 42).expect("should not be zero");
+    for value in 1..=10_000_u32 {
+        reservoir.add(value);
+    }
+
+    assert_eq!(reservoir.samples().len(), 500);
+    assert_eq!(reservoir.seen(), 10_000);
+
+    let median = reservoir.median().expect("should have samples");
+    assert!((median - 5_000.0).abs() < 750.0, "{median}");
+    let p90 = reservoir.percentile(90.0).expect("should have samples");
+    assert!(p90.abs_diff(9_000) < 500, "{p90}");
+}
+
+#[test_log::test]
+fn reservoir_is_reproducible() {
+    let mut first = Reservoir::with_seed(10, 7).expect("should not be zero");
+    let mut second = Reservoir::with_seed(10, 7).expect("should not be zero");
+    for value in 0..1_000 {
+        first.add(value);
+        second.add(value);
+    }
+    assert_eq!(first.samples(), second.samples());
+
+    let mut small = Reservoir::new(10).expect("should not be zero");
+    for value in [3, 1, 2] {
+        small.add(value);
+    }
+    assert_eq!(small.percentile(100.0), Some(3));
+    assert_eq!(small.median(), Some(2.0));
+}
+
+#[test_log::test]
+fn rate_last_update() {
+    let mut now = Millis::new(100);
+    let mut m = RateMetric::new(now);
+    assert_eq!(m.last_update(), Millis::new(100));
+
+    now += MillisDuration::from_millis(200);
+    m.update(now);
+    assert_eq!(m.last_update(), Millis::new(100));
+
+    now += MillisDuration::from_millis(300);
+    m.update(now);
+    assert_eq!(m.last_update(), Millis::new(600));
+}
+
+#[test_log::test]
+fn rate_update_callback() {
+    let mut now = Millis::new(0);
+    let mut m = RateMetric::new(now);
+    let mut history = Vec::new();
+
+    for _ in 0..10 {
+        m.add(10);
+        now += MillisDuration::from_millis(250);
+        m.update_with(now, |rate| history.push(rate));
+    }
+
+    assert_eq!(history, vec![40.0; 5]);
+}
+
+#[test_log::test]
+fn aggregate_publish_callback() {
+    let mut aggregate = AggregateMetric::new(2)
+        .expect("should not be zero")
+        .with_unit("ms");
+    let mut published = Vec::new();
+
+    for value in [1, 3, 5, 7, 9] {
+        aggregate.add_with(value, |values| published.push(*values));
+    }
+
+    assert_eq!(
+        published,
+        vec![
+            MinMaxAvg::new(1, 2.0, 3).with_unit("ms"),
+            MinMaxAvg::new(5, 6.0, 7).with_unit("ms"),
+        ]
+    );
+}
+
+#[test_log::test]
+fn weighted_average() {
+    let mut aggregate = AggregateMetric::new(3).expect("should not be zero");
+
+    aggregate.add_weighted(10.0, 3.0);
+    aggregate.add_weighted(50.0, 0.5);
+    aggregate.add(2.0);
+
+    let values = aggregate.values().expect("window should be complete");
+    assert_eq!(values.avg, (30.0 + 25.0 + 2.0) / 4.5);
+    assert_eq!(values.min, 2.0);
+    assert_eq!(values.max, 50.0);
+
+    aggregate.add_weighted(1.0, -1.0);
+    aggregate.add_weighted(1.0, f32::NAN);
+    assert_eq!(aggregate.pending_count(), 0);
+}
+
+#[cfg(feature = "graphite")]
+#[test_log::test]
+fn graphite_lines() {
+    use metricator::graphite;
+
+    let timestamp = Millis::new(1_700_000_000_500);
+    let mut aggregate = AggregateMetric::new(2).expect("should not be zero");
+    assert_eq!(
+        graphite::aggregate("game.latency", &aggregate, timestamp),
+        ""
+    );
+
+    aggregate.add(2.0);
+    aggregate.add(5.0);
+
+    assert_eq!(
+        graphite::aggregate("game.latency", &aggregate, timestamp),
+        "game.latency.min 2 1700000000
+game.latency.avg 3.5 1700000000
+game.latency.max 5 1700000000
+"
+    );
+
+    let mut rate = RateMetric::new(Millis::new(1_700_000_000_000));
+    rate.add(3);
+    rate.update(Millis::new(1_700_000_001_000));
+    assert_eq!(
+        graphite::rate("game.packets", &rate),
+        "game.packets 3 1700000001\n"
+    );
+
+    let mut frame_time = AggregateMetric::<u64>::new(2).expect("should not be zero");
+    frame_time.add(16);
+    frame_time.add(33);
+    assert_eq!(
+        graphite::aggregate("game.frame", &frame_time, timestamp),
+        "game.frame.min 16 1700000000
+game.frame.avg 24.5 1700000000
+game.frame.max 33 1700000000
+"
+    );
+}
+
+#[cfg(feature = "influx")]
+#[test_log::test]
+fn influx_line() {
+    use metricator::influx;
+
+    let timestamp = Millis::new(1_700_000_000_500);
+    let mut aggregate = AggregateMetric::new(3).expect("should not be zero");
+    assert_eq!(influx::aggregate("latency", &[], &aggregate, timestamp), "");
+
+    aggregate.add(2.0);
+    aggregate.add(5.0);
+    aggregate.add(8.0);
+
+    let line = influx::aggregate(
+        "latency",
+        &[("unit", "ms"), ("host", "eu west")],
+        &aggregate,
+        timestamp,
+    );
+    assert_eq!(
+        line,
+        "latency,unit=ms,host=eu\\ west min=2,avg=5,max=8 1700000000500000000\n"
+    );
+
+    let (head, timestamp) = line
+        .trim_end()
+        .rsplit_once(' ')
+        .expect("should have timestamp");
+    assert_eq!(
+        timestamp.parse::<u128>().expect("should be a number") / 1_000_000,
+        1_700_000_000_500
+    );
+    assert!(head.ends_with("min=2,avg=5,max=8"));
+
+    let mut frame_time = AggregateMetric::<u64>::new(2).expect("should not be zero");
+    frame_time.add(16);
+    frame_time.add(33);
+    assert_eq!(
+        influx::aggregate("frame", &[], &frame_time, Millis::new(1_000)),
+        "frame min=16,avg=24.5,max=33 1000000000\n"
+    );
+}
+
+#[test_log::test]
+fn rate_rebase_keeps_rate() {
+    let mut m = RateMetric::new(Millis::new(0));
+    m.add(20);
+    m.update(Millis::new(1000));
+    assert_eq!(m.rate(), 20.0);
+
+    m.add(5);
+    m.rebase(Millis::new(30_000));
+    assert_eq!(m.pending_count(), 0);
+    assert_eq!(m.rate(), 20.0);
+
+    m.update(Millis::new(30_250));
+    assert_eq!(m.rate(), 20.0);
+
+    m.add(10);
+    m.update(Millis::new(31_000));
+    assert_eq!(m.rate(), 10.0);
+
+    m.reset(Millis::new(40_000));
+    assert_eq!(m.rate(), 0.0);
+    assert_eq!(m.last_update(), Millis::new(40_000));
+    assert_eq!(m.total(), 35);
+}
+
+#[test_log::test]
+fn aggregate_u64_large_values() {
+    let mut aggregate = AggregateMetric::<u64>::new(3).expect("should not be zero");
+
+    // Above 2^24, where consecutive integers are no longer representable as f32
+    aggregate.add(5_000_000_001);
+    aggregate.add(5_000_000_003);
+    aggregate.add(5_000_000_005);
+
+    let values = aggregate.values().expect("should have values");
+    assert_eq!(values.min, 5_000_000_001);
+    assert_eq!(values.max, 5_000_000_005);
+    assert!(
+        (values.avg / 5_000_000_003.0 - 1.0).abs() < 1e-6,
+        "{}",
+        values.avg
+    );
+    let average = aggregate.average_f64().expect("should have values");
+    assert!((average - 5_000_000_003.0).abs() < 1e-3, "{average}");
+    assert_eq!(
+        aggregate.snapshot().expect("should have snapshot").sum,
+        15_000_000_009
+    );
+
+    // Near u64::MAX the sum saturates, but the average stays close to the values
+    aggregate.add(u64::MAX);
+    aggregate.add(u64::MAX - 1_000);
+    aggregate.add(u64::MAX);
+    let expected = u64::MAX as f64;
+    let average = aggregate.average_f64().expect("should have values");
+    assert!((average / expected - 1.0).abs() < 1e-12, "{average}");
+    assert_eq!(
+        aggregate.snapshot().expect("should have snapshot").sum,
+        u64::MAX
+    );
+}
+
+#[test_log::test]
+fn aggregate_i64_negative_values() {
+    let mut aggregate = AggregateMetric::<i64>::new(4).expect("should not be zero");
+
+    aggregate.add(-20_000_001);
+    aggregate.add(-20_000_003);
+    aggregate.add(20_000_001);
+    aggregate.add(16_777_219);
+
+    let values = aggregate.values().expect("should have values");
+    assert_eq!(values.min, -20_000_003);
+    assert_eq!(values.max, 20_000_001);
+    assert!((values.avg - -805_696.0).abs() < 0.5, "{}", values.avg);
+    let average = aggregate.average_f64().expect("should have values");
+    assert!((average - -805_696.0).abs() < 1e-6, "{average}");
+}
+
+#[test_log::test]
+fn rate_saturating_add() {
+    let mut m = RateMetric::new_saturating(Millis::new(0));
+    m.add(u32::MAX);
+    m.add(u32::MAX);
+    assert_eq!(m.pending_count(), u32::MAX);
+    assert_eq!(m.total(), 2 * u64::from(u32::MAX));
+
+    let mut wrapping = RateMetric::new(Millis::new(0));
+    wrapping.add(u32::MAX);
+    wrapping.add(2);
+    assert_eq!(wrapping.pending_count(), 1);
+}
+
+#[test_log::test]
+fn counter_rate() {
+    let mut counter = CounterRate::new();
+    assert_eq!(counter.rate_from_counter(1_000, Millis::new(0)), 0.0);
+    assert_eq!(counter.rate_from_counter(1_500, Millis::new(500)), 1_000.0);
+    assert_eq!(
+        counter.rate_from_counter(3_500, Millis::new(2_500)),
+        1_000.0
+    );
+    assert_eq!(
+        counter.rate_from_counter(3_600, Millis::new(2_500)),
+        1_000.0
+    );
+
+    // The source was restarted
+    assert_eq!(counter.rate_from_counter(100, Millis::new(3_500)), 0.0);
+    assert_eq!(counter.rate_from_counter(400, Millis::new(4_500)), 300.0);
+    assert_eq!(counter.rate(), 300.0);
+}
+
+#[test_log::test]
+fn rate_state_round_trip() {
+    let mut m = RateMetric::with_smoothing(Millis::new(0), 1.0, 0.5)
+        .expect("alpha should be valid")
+        .with_unit("pkt");
+    m.add(10);
+    m.update(Millis::new(1000));
+    m.add(3);
+    m.add_weighted(0.5);
+
+    let state: RateMetricState = m.to_state();
+    assert_eq!(state.count, 3);
+    assert_eq!(state.average, 5.0);
+
+    let mut restored = RateMetric::from_state(state);
+    assert_eq!(restored.to_state(), state);
+
+    m.update(Millis::new(2000));
+    restored.update(Millis::new(2000));
+    assert_eq!(restored.rate().to_bits(), m.rate().to_bits());
+    assert_eq!(restored.to_string(), "rate:4.25pkt/s");
+}
+
+#[test_log::test]
+fn rate_clamp() {
+    let mut m = RateMetric::new(Millis::new(0)).with_clamp(0.0, 1_000.0);
+    m.set_interval(MillisDuration::from_millis(1))
+        .expect("interval should be valid");
+    m.add(1_000_000);
+    m.update(Millis::new(1));
+    assert_eq!(m.rate(), 1_000.0);
+    assert_eq!(m.total(), 1_000_000);
+
+    m.add(500);
+    m.update(Millis::new(1_001));
+    assert_eq!(m.rate(), 500.0);
+}
+
+#[test_log::test]
+fn aggregate_has_data() {
+    let mut aggregate = AggregateMetric::<u32>::new(2).expect("should not be zero");
+    assert!(!aggregate.has_data());
+
+    aggregate.add(1);
+    assert!(!aggregate.has_data());
+
+    aggregate.add(3);
+    assert!(aggregate.has_data());
+
+    aggregate.reset();
+    assert!(aggregate.has_data());
+
+    aggregate.clear();
+    assert!(!aggregate.has_data());
+}
+
+#[test_log::test]
+fn all_max_values_window() {
+    let mut aggregate = AggregateMetric::<u8>::new(3).expect("should not be zero");
+    aggregate.add(u8::MAX);
+    assert_eq!(aggregate.current_min(), Some(u8::MAX));
+    assert_eq!(aggregate.current_max(), Some(u8::MAX));
+    aggregate.add(u8::MAX);
+    aggregate.add(u8::MAX);
+
+    let values = aggregate.values().expect("should calculate values");
+    assert_eq!(values.min, u8::MAX);
+    assert_eq!(values.max, u8::MAX);
+
+    // The next window must not see the previous window, nor the sentinels
+    aggregate.add(1);
+    aggregate.add(2);
+    aggregate.add(3);
+    let values = aggregate.values().expect("should calculate values");
+    assert_eq!((values.min, values.max), (1, 3));
+
+    let mut single = AggregateMetric::<f32>::new(1).expect("should not be zero");
+    single.add(f32::MAX);
+    assert_eq!(
+        single.values().expect("should calculate values").as_tuple(),
+        (f32::MAX, f32::MAX, f32::MAX)
+    );
+}
+
+#[test_log::test]
+fn all_min_values_window() {
+    let mut aggregate = AggregateMetric::<i32>::new(2).expect("should not be zero");
+    aggregate.add(i32::MIN);
+    assert_eq!(aggregate.current_min(), Some(i32::MIN));
+    assert_eq!(aggregate.current_max(), Some(i32::MIN));
+    aggregate.add(i32::MIN);
+
+    let values = aggregate.values().expect("should calculate values");
+    assert_eq!(values.min, i32::MIN);
+    assert_eq!(values.max, i32::MIN);
+
+    let mut other = AggregateMetric::<i32>::new(2).expect("should not be zero");
+    other.add(i32::MIN);
+    let mut merged = AggregateMetric::<i32>::new(2).expect("should not be zero");
+    merged.merge(&other).expect("metrics should be compatible");
+    merged.add(i32::MIN);
+    let values = merged.values().expect("should calculate values");
+    assert_eq!((values.min, values.max), (i32::MIN, i32::MIN));
+
+    let mut single = AggregateMetric::<f32>::new(1).expect("should not be zero");
+    single.add(f32::MIN);
+    assert_eq!(
+        single.values().expect("should calculate values").as_tuple(),
+        (f32::MIN, f32::MIN, f32::MIN)
+    );
+}
+
+#[test_log::test]
+fn duration_aggregate() {
+    let mut durations = DurationAggregate::new(3).expect("should not be zero");
+    assert_eq!(durations.average(), None);
+    assert_eq!(durations.to_string(), "no data");
+
+    durations.add(MillisDuration::from_millis(16));
+    durations.add(MillisDuration::from_millis(17));
+    durations.add(MillisDuration::from_millis(33));
+
+    assert_eq!(durations.min(), Some(MillisDuration::from_millis(16)));
+    assert_eq!(durations.max(), Some(MillisDuration::from_millis(33)));
+    assert_eq!(durations.average(), Some(MillisDuration::from_millis(22)));
+    assert_eq!(durations.to_string(), "min:16ms, avg:22ms, max:33ms");
+}
+
+#[test_log::test]
+fn byte_rate_formatting() {
+    let binary = |bytes| ByteRate::new(bytes, BytePrefix::Binary).to_string();
+    assert_eq!(binary(0.0), "0 B/s");
+    assert_eq!(binary(1023.0), "1023 B/s");
+    assert_eq!(binary(1024.0), "1.0 KiB/s");
+    assert_eq!(binary(1_572_864.0), "1.5 MiB/s");
+    assert_eq!(binary(1024.0 * 1024.0 * 1024.0), "1.0 GiB/s");
+    assert_eq!(binary(2.0 * 1024.0_f32.powi(4)), "2.0 TiB/s");
+    assert_eq!(binary(2048.0 * 1024.0_f32.powi(4)), "2048.0 TiB/s");
+
+    // Values that round up to the base move to the next prefix
+    assert_eq!(binary(1023.4), "1023 B/s");
+    assert_eq!(binary(1023.6), "1.0 KiB/s");
+    assert_eq!(binary(1_048_524.0), "1023.9 KiB/s");
+    assert_eq!(binary(1_048_575.0), "1.0 MiB/s");
+
+    let decimal = |bytes| ByteRate::new(bytes, BytePrefix::Decimal).to_string();
+    assert_eq!(decimal(999.0), "999 B/s");
+    assert_eq!(decimal(1000.0), "1.0 kB/s");
+    assert_eq!(decimal(1024.0), "1.0 kB/s");
+    assert_eq!(decimal(1_500_000.0), "1.5 MB/s");
+    assert_eq!(decimal(999.7), "1.0 kB/s");
+    assert_eq!(decimal(999_960.0), "1.0 MB/s");
+
+    let mut m = RateMetric::new(Millis::new(0));
+    m.add(1_572_864);
+    m.update(Millis::new(1000));
+    assert_eq!(m.byte_rate(BytePrefix::Binary).to_string(), "1.5 MiB/s");
+}
+
+#[test_log::test]
+fn ratio_metric() {
+    let mut ratio = RatioMetric::new();
+    assert_eq!(ratio.ratio(), None);
+    assert_eq!(ratio.smoothed_ratio(), None);
+
+    ratio.record_hit();
+    ratio.record_hit();
+    ratio.record_hit();
+    ratio.record_miss();
+    assert_eq!(ratio.ratio(), Some(0.75));
+    assert_eq!((ratio.hits(), ratio.misses()), (3, 1));
+
+    let mut smoothed = RatioMetric::with_smoothing(0.5).expect("alpha should be valid");
+    smoothed.record_hit();
+    smoothed.record_miss();
+    smoothed.record_miss();
+    assert_eq!(smoothed.smoothed_ratio(), Some(0.25));
+    assert!(RatioMetric::with_smoothing(2.0).is_err());
+}
+
+#[test_log::test]
+fn aggregate_set_threshold() {
+    let mut aggregate = AggregateMetric::<u32>::new(10).expect("should not be zero");
+    aggregate.add_slice(&[1, 2, 3, 4]);
+    assert!(!aggregate.has_data());
+
+    assert_eq!(aggregate.set_threshold(0), Err(MetricError::ZeroThreshold));
+    assert_eq!(aggregate.threshold(), 10);
+
+    aggregate.set_threshold(3).expect("should not be zero");
+    assert_eq!(aggregate.pending_count(), 0);
+    let snapshot = aggregate.snapshot().expect("should have published");
+    assert_eq!((snapshot.count, snapshot.avg), (4, 2.5));
+
+    aggregate.add_slice(&[5, 6]);
+    aggregate.set_threshold(4).expect("should not be zero");
+    assert_eq!(aggregate.pending_count(), 2);
+}
+
+#[test_log::test]
+fn aggregate_history() {
+    let mut aggregate = AggregateMetric::<u32>::new_with_history(2, 3).expect("should be valid");
+    assert_eq!(aggregate.history().count(), 0);
+
+    aggregate.add_slice(&[1, 3, 2, 4, 3, 5, 4, 6]);
+
+    let averages: Vec<f32> = aggregate.history().map(|values| values.avg).collect();
+    assert_eq!(averages, [3.0, 4.0, 5.0]);
+    assert_eq!(
+        aggregate
+            .history()
+            .last()
+            .expect("should have history")
+            .as_tuple(),
+        (4, 5.0, 6)
+    );
+
+    assert_eq!(
+        AggregateMetric::<u32>::new_with_history(2, 0).err(),
+        Some(MetricError::ZeroCapacity)
+    );
+}
+
+#[test_log::test]
+fn stale_metrics() {
+    let max_age = MillisDuration::from_millis(2_000);
+
+    let mut rate = RateMetric::new(Millis::new(0));
+    assert!(!rate.is_stale(Millis::new(2_000), max_age));
+    assert!(rate.is_stale(Millis::new(2_001), max_age));
+    rate.update(Millis::new(2_001));
+    assert!(!rate.is_stale(Millis::new(2_001), max_age));
+
+    let mut aggregate =
+        AggregateMetric::<u32>::with_time_window(Millis::new(0), MillisDuration::from_millis(500))
+            .expect("window should be valid");
+    assert!(aggregate.is_stale(Millis::new(0), max_age));
+    aggregate.add_at(Millis::new(1_000), 4);
+    assert!(!aggregate.is_stale(Millis::new(3_000), max_age));
+    assert!(aggregate.is_stale(Millis::new(3_001), max_age));
+}
+
+#[test_log::test]
+fn min_max_avg_display_padding() {
+    let values = MinMaxAvg::new(1, 2.5, 4).with_unit("ms");
+    assert_eq!(
+        format!("{:>30}|", values),
+        "   min:1ms, avg:2.5ms, max:4ms|"
+    );
+    assert_eq!(
+        format!("{:<30}|", values),
+        "min:1ms, avg:2.5ms, max:4ms   |"
+    );
+    assert_eq!(
+        format!("{:*^31}", values),
+        "**min:1ms, avg:2.5ms, max:4ms**"
+    );
+    assert_eq!(format!("{:5}", values), "min:1ms, avg:2.5ms, max:4ms");
+
+    // A precision sets the decimals of the values instead of truncating the text
+    let floats = MinMaxAvg::new(1.0, 2.5, 4.0).with_unit("ms");
+    assert_eq!(
+        format!("{:.2}", floats),
+        "min:1.00ms, avg:2.50ms, max:4.00ms"
+    );
+    assert_eq!(
+        format!("{:>33.1}|", floats),
+        "  min:1.0ms, avg:2.5ms, max:4.0ms|"
+    );
+    assert_eq!(format!("{:.2}", values), "min:1ms, avg:2.50ms, max:4ms");
+    assert_eq!(
+        format!("{:>33}|", floats.with_precision(1)),
+        "  min:1.0ms, avg:2.5ms, max:4.0ms|"
+    );
+}
+
+#[test_log::test]
+fn decaying_aggregate() {
+    let mut aggregate = DecayingAggregate::new(MillisDuration::from_millis(1_000)).with_unit("ms");
+    assert_eq!(aggregate.values(), None);
+
+    aggregate.add(Millis::new(0), 10.0);
+    aggregate.add(Millis::new(0), 20.0);
+    let values = aggregate.values().expect("should have values");
+    assert_eq!(values.as_tuple(), (10.0, 15.0, 20.0));
+
+    // After a long time, the old samples have almost no weight left
+    aggregate.add(Millis::new(10_000), 100.0);
+    let values = aggregate.values().expect("should have values");
+    assert!((values.avg - 100.0).abs() < 0.01);
+    assert!((values.min - 15.0).abs() < 0.01);
+    assert_eq!(values.max, 100.0);
+
+    // One time constant later, the min has moved 1 - 1/e of the way toward the mean
+    aggregate.add(Millis::new(11_000), 100.0);
+    let values = aggregate.values().expect("should have values");
+    let expected_min = 100.0 - (100.0 - 15.0) * (-1.0_f32).exp();
+    assert!((values.min - expected_min).abs() < 0.1);
+    assert_eq!(values.unit, "ms");
+}
+
+#[test_log::test]
+fn fixed_aggregate() {
+    let mut aggregate = FixedAggregate::<u32, 4>::new().with_unit("ms");
+    assert!(aggregate.is_empty());
+    assert_eq!(aggregate.values(), None);
+    assert_eq!(aggregate.median(), None);
+
+    aggregate.add(9);
+    aggregate.add(1);
+    aggregate.add(5);
+    assert_eq!(aggregate.median(), Some(5.0));
+    assert_eq!(
+        aggregate.values().expect("should have values").to_string(),
+        "min:1ms, avg:5ms, max:9ms"
+    );
+
+    aggregate.add(3);
+    aggregate.add(7);
+    assert_eq!(aggregate.len(), 4);
+    assert_eq!(aggregate.median(), Some(4.0));
+    assert_eq!(
+        aggregate.values().expect("should have values").as_tuple(),
+        (1, 4.0, 7)
+    );
+}
+
+#[test_log::test]
+fn throughput_metric() {
+    let mut throughput = ThroughputMetric::new(Millis::new(0), 3).expect("should not be zero");
+    assert_eq!(throughput.to_string(), "0/s, latency no data");
+
+    throughput.record(Millis::new(100), MillisDuration::from_millis(1));
+    throughput.record(Millis::new(200), MillisDuration::from_millis(2));
+    throughput.record(Millis::new(300), MillisDuration::from_millis(9));
+    assert_eq!(throughput.rate(), 0.0);
+
+    throughput.update(Millis::new(500));
+    assert_eq!(throughput.rate(), 6.0);
+    assert_eq!(
+        throughput
+            .latency()
+            .expect("should have latency")
+            .as_tuple(),
+        (1, 4.0, 9)
+    );
+    assert_eq!(
+        throughput.to_string(),
+        "6/s, latency min:1ms, avg:4ms, max:9ms"
+    );
+}
+
+#[test_log::test]
+fn aggregate_average_f64() {
+    let mut aggregate = AggregateMetric::<u64>::new(2).expect("should not be zero");
+    assert_eq!(aggregate.average_f64(), None);
+
+    aggregate.add(16_777_216);
+    aggregate.add(16_777_218);
+
+    // 2^24 + 1 can't be represented as f32
+    assert_eq!(aggregate.average(), Some(16_777_216.0));
+    assert_eq!(aggregate.average_f64(), Some(16_777_217.0));
+}
+
+#[test_log::test]
+fn aggregate_sample_rate() {
+    let mut aggregate =
+        AggregateMetric::<u32>::with_time_window(Millis::new(0), MillisDuration::from_millis(500))
+            .expect("window should be valid");
+    assert_eq!(aggregate.sample_rate(Millis::new(0)), 0.0);
+
+    for i in 0..=20 {
+        aggregate.add_at(Millis::new(i * 50), 1);
+    }
+    assert_eq!(aggregate.sample_rate(Millis::new(1_000)), 20.0);
+
+    // The source stalls, so the rate falls
+    assert_eq!(aggregate.sample_rate(Millis::new(5_000)), 0.25);
+}
+
+#[test_log::test]
+fn quantile_summary() {
+    let mut aggregate = AggregateMetric::<u32>::new_buffered(100).expect("should not be zero");
+    assert_eq!(aggregate.summary(), None);
+
+    aggregate.extend(1..=100);
+
+    let summary = aggregate.summary().expect("should have summary");
+    assert_eq!(
+        summary,
+        QuantileSummary {
+            p50: 50,
+            p90: 90,
+            p99: 99,
+            unit: "",
+        }
+    );
+    assert_eq!(summary.to_string(), "p50:50 p90:90 p99:99");
+
+    let mut unbuffered = AggregateMetric::<u32>::new(1).expect("should not be zero");
+    unbuffered.add(1);
+    assert_eq!(unbuffered.summary(), None);
+}
+
+#[test_log::test]
+fn rate_with_u64_count() {
+    let mut m = RateMetric::builder()
+        .unit("B")
+        .build_with_count::<u64>(Millis::new(0))
+        .expect("alpha should be valid");
+    m.add(6_000_000_000);
+    m.add(u64::from(u32::MAX));
+    assert_eq!(m.pending_count(), 6_000_000_000 + u64::from(u32::MAX));
+    assert_eq!(m.total(), 6_000_000_000 + u64::from(u32::MAX));
+
+    m.update(Millis::new(1_000));
+    assert_eq!(m.rate(), 10_294_967_295.0);
+    assert_eq!(m.pending_count(), 0);
+    assert_eq!(m.to_state().count, 0_u64);
+}
+
+#[test_log::test]
+fn rate_unit() {
+    let m = RateMetric::new(Millis::new(0));
+    assert_eq!(m.unit(), "");
+
+    let m = m.with_unit("pkt");
+    assert_eq!(m.unit(), "pkt");
+    assert_eq!(m.to_string(), "rate:0pkt/s");
+}
+
+#[cfg(feature = "openmetrics")]
+#[test_log::test]
+fn openmetrics_histogram_with_exemplar() {
+    use metricator::openmetrics::{self, Exemplar};
+
+    let mut histogram = Histogram::new(&[1.0, 5.0, 10.0]).expect("should be sorted");
+    let mut aggregate = AggregateMetric::new(4).expect("should not be zero");
+    for value in [0.5, 2.0, 4.0, 9.5] {
+        histogram.record(value);
+        aggregate.add(value);
+    }
+
+    let exemplar = Exemplar::at_max(&aggregate, "4bf92f3577b34da6");
+    let mut out = String::new();
+    openmetrics::write_histogram(
+        &mut out,
+        "latency",
+        "Request latency in ms",
+        &[("region", "eu")],
+        &histogram,
+        exemplar,
+    );
+    openmetrics::write_eof(&mut out);
+
+    assert_eq!(out, include_str!("golden/openmetrics_histogram.txt"));
+}
+
+#[cfg(feature = "openmetrics")]
+#[test_log::test]
+fn openmetrics_registry_eof() {
+    let mut registry = MetricRegistry::new(Millis::new(0), 2).expect("should not be zero");
+    registry.rate("packets").add(5);
+    registry.update_all(Millis::new(1_000));
+
+    assert_eq!(
+        metricator::openmetrics::render_registry(&registry),
+        "# HELP packets packets per second
+# TYPE packets gauge
+packets 5
+# EOF
+"
+    );
+}
+
+#[test_log::test]
+fn aggregate_normalized_last() {
+    let mut aggregate = AggregateMetric::<i32>::new_buffered(4).expect("should not be zero");
+    assert_eq!(aggregate.normalized_last(), None);
+
+    aggregate.add(10);
+    assert_eq!(aggregate.normalized_last(), Some(0.5));
+
+    aggregate.add(20);
+    aggregate.add(15);
+    assert_eq!(aggregate.normalized_last(), Some(0.5));
+
+    // Completes the window, so the completed window is used
+    aggregate.add(12);
+    assert_eq!(aggregate.pending_count(), 0);
+    assert_eq!(aggregate.normalized_last(), Some(0.2));
+
+    aggregate.add(-5);
+    aggregate.add(5);
+    assert_eq!(aggregate.normalized_last(), Some(1.0));
+
+    aggregate.reset();
+    assert_eq!(aggregate.normalized_last(), None);
+    aggregate.add(15);
+    assert_eq!(aggregate.normalized_last(), Some(0.5));
+
+    aggregate.reset_keep_last();
+    assert_eq!(aggregate.normalized_last(), None);
+}
+
+#[test_log::test]
+fn rate_fixed_windows() {
+    let mut m = RateMetric::new(Millis::new(0)).with_fixed_windows();
+    m.set_interval(MillisDuration::from_millis(1_000))
+        .expect("interval should be valid");
+
+    m.add(10);
+    m.update(Millis::new(1_300));
+    assert_eq!(m.rate(), 10.0);
+    assert_eq!(m.last_update(), Millis::new(1_000));
+
+    m.add(5);
+    m.update(Millis::new(1_900));
+    assert_eq!(m.rate(), 10.0);
+    m.update(Millis::new(2_100));
+    assert_eq!(m.rate(), 5.0);
+    assert_eq!(m.last_update(), Millis::new(2_000));
+
+    m.add(20);
+    m.update(Millis::new(4_500));
+    assert_eq!(m.rate(), 10.0);
+    assert_eq!(m.last_update(), Millis::new(4_000));
+}
+
+#[test_log::test]
+fn rate_bounds() {
+    let mut m = RateMetric::new(Millis::new(0));
+    m.set_interval(MillisDuration::from_millis(1_000))
+        .expect("interval should be valid");
+    assert_eq!(m.min_rate(), 0.0);
+
+    for (seconds, count) in [(1, 8), (2, 0), (3, 3), (4, 5)] {
+        m.add(count);
+        m.update(Millis::new(seconds * 1_000));
+    }
+    assert_eq!(m.min_rate(), 3.0);
+    assert_eq!(m.peak_rate(), 8.0);
+
+    m.reset_rate_bounds();
+    assert_eq!((m.min_rate(), m.peak_rate()), (0.0, 0.0));
+}
+
+#[test_log::test]
+fn combine_aggregates() {
+    let mut europe = AggregateMetric::<u32>::new(2).expect("should not be zero");
+    let mut asia = AggregateMetric::<u32>::new(4).expect("should not be zero");
+    let idle = AggregateMetric::<u32>::new(1).expect("should not be zero");
+    assert_eq!(metricator::combine(&[&europe, &idle]), None);
+
+    europe.add_slice(&[10, 20]);
+    asia.add_slice(&[30, 40, 50, 60]);
+
+    let combined = metricator::combine(&[&europe, &idle, &asia]).expect("should have data");
+    assert_eq!(combined.as_tuple(), (10, 35.0, 60));
+}
+
+/// Returns the values `0..count` in a scrambled but deterministic order.
+#[cfg(feature = "tdigest")]
+fn scrambled(count: u64) -> impl Iterator<Item = u64> {
+    (0..count).map(move |i| i * 7_919 % count)
+}
+
+#[cfg(feature = "tdigest")]
+#[test_log::test]
+fn tdigest_uniform() {
+    let mut digest = metricator::TDigest::new(100).expect("should not be zero");
+    assert_eq!(digest.quantile(0.5), None);
+
+    for value in scrambled(100_000) {
+        digest.add(value as f64);
+    }
+
+    assert_eq!(digest.count(), 100_000);
+    assert_eq!(digest.min(), Some(0.0));
+    assert_eq!(digest.max(), Some(99_999.0));
+    for (q, tolerance) in [(0.5, 500.0), (0.9, 300.0), (0.99, 50.0), (0.999, 25.0)] {
+        let estimate = digest.quantile(q).expect("should have values");
+        let expected = q * 100_000.0;
+        assert!(
+            (estimate - expected).abs() < tolerance,
+            "q{q}: {estimate} is not close to {expected}"
+        );
+    }
+    assert_eq!(digest.quantile(1.5), None);
+}
+
+#[cfg(feature = "tdigest")]
+#[test_log::test]
+fn tdigest_merged_exponential() {
+    // Exponentially distributed values from the inverse of the CDF
+    let count = 50_000;
+    let exponential = |i: u64| -(1.0 - (i as f64 + 0.5) / count as f64).ln();
+
+    let mut first = metricator::TDigest::default();
+    let mut second = metricator::TDigest::default();
+    for i in scrambled(count) {
+        if i % 2 == 0 {
+            first.add(exponential(i));
+        } else {
+            second.add(exponential(i));
+        }
+    }
+    first.merge(&second);
+
+    assert_eq!(first.count(), count);
+    for q in [0.5_f64, 0.9, 0.99] {
+        let estimate = first.quantile(q).expect("should have values");
+        let expected = -(1.0 - q).ln();
+        assert!(
+            (estimate - expected).abs() / expected < 0.01,
+            "q{q}: {estimate} is not close to {expected}"
+        );
+    }
+
+    // The extremes survive the merge, also into an empty digest
+    let (min, max) = (exponential(0), exponential(count - 1));
+    assert_eq!((first.min(), first.max()), (Some(min), Some(max)));
+    let mut empty = metricator::TDigest::default();
+    empty.merge(&first);
+    assert_eq!((empty.min(), empty.max()), (Some(min), Some(max)));
+    assert_eq!(empty.quantile(0.0), Some(min));
+    assert_eq!(empty.quantile(1.0), Some(max));
+}
+
+#[test_log::test]
+fn aggregate_rejected_count() {
+    let mut aggregate = AggregateMetric::<f32>::new(2).expect("should not be zero");
+    assert_eq!(aggregate.rejected_count(), 0);
+
+    assert_eq!(aggregate.try_add(f32::NAN), Err(MetricError::NonFinite));
+    aggregate.add(f32::INFINITY);
+    aggregate.add(1.0);
+    assert_eq!(aggregate.try_add(f32::MAX), Err(MetricError::Overflow));
+
+    assert_eq!(aggregate.rejected_count(), 2);
+    assert_eq!(aggregate.pending_count(), 1);
+}
+
+#[test_log::test]
+fn event_rate() {
+    let mut events = EventRate::new(3).expect("should not be zero");
+    assert_eq!(events.rate(), None);
+
+    events.record(Millis::new(0));
+    assert_eq!(events.rate(), None);
+    events.record(Millis::new(500));
+    assert_eq!(events.rate(), Some(2.0));
+
+    // A burst quickly raises the rate, as the oldest event is evicted
+    events.record(Millis::new(10_000));
+    events.record(Millis::new(10_100));
+    events.record(Millis::new(10_200));
+    assert_eq!(events.len(), 3);
+    assert_eq!(events.rate(), Some(10.0));
+
+    assert!(EventRate::new(0).is_err());
+}
+
+#[test_log::test]
+fn aggregate_default_publishes_every_value() {
+    #[derive(Default)]
+    struct Stats {
+        latency: AggregateMetric<u32>,
+    }
+
+    let mut stats = Stats::default();
+    assert_eq!(stats.latency.threshold(), 1);
+    assert_eq!(stats.latency.values(), None);
+
+    stats.latency.add(7);
+    assert_eq!(stats.latency.values(), Some(MinMaxAvg::new(7, 7.0, 7)));
+    stats.latency.add(3);
+    assert_eq!(stats.latency.values(), Some(MinMaxAvg::new(3, 3.0, 3)));
+}
+
+#[test_log::test]
+fn rate_force_update() {
+    let mut m = RateMetric::new(Millis::new(0));
+    m.add(5);
+
+    m.update(Millis::new(100));
+    assert_eq!(m.rate(), 0.0);
+
+    m.force_update(Millis::new(100));
+    assert_eq!(m.rate(), 50.0);
+    assert_eq!(m.pending_count(), 0);
+    assert_eq!(m.last_update(), Millis::new(100));
+
+    // No elapsed time is ignored instead of dividing by zero
+    m.add(1);
+    m.force_update(Millis::new(100));
+    assert_eq!(m.rate(), 50.0);
+    assert_eq!(m.pending_count(), 1);
+}
+
+#[test_log::test]
+fn rate_jitter() {
+    let mut plain = RateMetric::new(Millis::new(0));
+    plain.increment_at(Millis::new(10));
+    plain.increment_at(Millis::new(20));
+    plain.increment_at(Millis::new(30));
+    assert_eq!(plain.jitter(), None);
+    assert_eq!(plain.pending_count(), 3);
+
+    let mut m = RateMetric::new_with_jitter(Millis::new(0));
+    m.increment_at(Millis::new(0));
+    m.increment_at(Millis::new(10));
+    assert_eq!(m.jitter(), None);
+
+    // Gaps of 10, 30, 10 and 30 ms deviate 10 ms from their mean
+    m.increment_at(Millis::new(40));
+    m.increment_at(Millis::new(50));
+    m.increment_at(Millis::new(80));
+    assert_eq!(m.jitter(), Some(MillisDuration::from_millis(10)));
+    assert_eq!(m.pending_count(), 5);
+}
+
+#[test_log::test]
+fn min_max_avg_format_parts() {
+    let values = MinMaxAvg::new(1, 2.5, 4).with_unit("ms");
+    assert_eq!(
+        values.format_parts(&[MetricPart::Avg, MetricPart::Unit]),
+        "avg:2.5ms"
+    );
+    assert_eq!(
+        values.format_parts(&[MetricPart::Min, MetricPart::Max]),
+        "min:1, max:4"
+    );
+    assert_eq!(
+        values.format_parts(&[MetricPart::Unit, MetricPart::Range, MetricPart::Avg]),
+        "range:3ms, avg:2.5ms"
+    );
+    assert_eq!(values.format_parts(&[]), "");
+
+    let values = MinMaxAvg::new(1.0, 2.34, 4.0).with_precision(1);
+    assert_eq!(
+        values.format_parts(&[MetricPart::Min, MetricPart::Avg]),
+        "min:1.0, avg:2.3"
+    );
+}
+
+#[test_log::test]
+fn rate_invalid_interval() {
+    let now = Millis::new(0);
+    assert_eq!(
+        RateMetric::with_interval(now, 0.0).err(),
+        Some(MetricError::InvalidInterval)
+    );
+    assert_eq!(
+        RateMetric::with_interval(now, -1.0).err(),
+        Some(MetricError::InvalidInterval)
+    );
+    assert_eq!(
+        RateMetric::with_smoothing(now, -1.0, 0.5).err(),
+        Some(MetricError::InvalidInterval)
+    );
+    assert!(RateMetric::with_interval(now, 0.5).is_ok());
+
+    assert_eq!(
+        RateMetric::builder()
+            .interval(MillisDuration::from_millis(0))
+            .build(now)
+            .err(),
+        Some(MetricError::InvalidInterval)
+    );
+
+    let mut m = RateMetric::new(now);
+    assert_eq!(
+        m.set_interval(MillisDuration::from_millis(0)),
+        Err(MetricError::InvalidInterval)
+    );
+    assert_eq!(m.interval(), MillisDuration::from_millis(500));
+
+    assert_eq!(
+        MultiRateMetric::new(
+            now,
+            &[
+                MillisDuration::from_millis(1_000),
+                MillisDuration::from_millis(0)
+            ]
+        )
+        .err(),
+        Some(MetricError::InvalidInterval)
+    );
+}
+
+#[test_log::test]
+fn aggregate_last_sample_time() {
+    let mut aggregate = AggregateMetric::<u32>::new(2).expect("threshold should be non-zero");
+    assert_eq!(aggregate.last_sample_time(), None);
+
+    aggregate.add(1);
+    assert_eq!(aggregate.last_sample_time(), None);
+
+    aggregate.add_at(Millis::new(250), 3);
+    assert_eq!(aggregate.last_sample_time(), Some(Millis::new(250)));
+    assert_eq!(aggregate.values(), Some(MinMaxAvg::new(1, 2.0, 3)));
+
+    aggregate.add(5);
+    assert_eq!(aggregate.last_sample_time(), Some(Millis::new(250)));
+
+    aggregate.clear();
+    assert_eq!(aggregate.last_sample_time(), None);
+}
+
+#[test_log::test]
+fn merge_incompatible() {
+    let mut buffered = AggregateMetric::<u32>::new_buffered(2).expect("should not be zero");
+    let mut plain = AggregateMetric::<u32>::new(2).expect("should not be zero");
+    plain.add(3);
+    plain.add(5);
+    plain.add(7);
+
+    buffered.add(1);
+    assert_eq!(buffered.merge(&plain), Err(MetricError::Incompatible));
+    assert_eq!(plain.merge(&buffered), Err(MetricError::Incompatible));
+    assert_eq!(buffered.pending_count(), 1);
+
+    buffered.add(9);
+    assert_eq!(buffered.median(), Some(5.0));
+
+    let mut geometric = AggregateMetric::<f32>::new_geometric(2).expect("should not be zero");
+    let mut arithmetic = AggregateMetric::<f32>::new(2).expect("should not be zero");
+    geometric.add(4.0);
+    arithmetic.add(-1.0);
+    assert_eq!(geometric.merge(&arithmetic), Err(MetricError::Incompatible));
+    assert_eq!(arithmetic.merge(&geometric), Err(MetricError::Incompatible));
+    assert_eq!(geometric.values(), None);
+
+    let mut untrimmed = AggregateMetric::<f32>::new_trimmed(4, 0.0).expect("should be valid");
+    let mut trimmed = AggregateMetric::<f32>::new_trimmed(4, 0.25).expect("should be valid");
+    trimmed.add(1.0);
+    assert_eq!(untrimmed.merge(&trimmed), Err(MetricError::Incompatible));
+    assert_eq!(untrimmed.pending_count(), 0);
+
+    let mut same = AggregateMetric::<f32>::new_trimmed(4, 0.25).expect("should be valid");
+    assert_eq!(same.merge(&trimmed), Ok(()));
+    assert_eq!(same.pending_count(), 1);
+}